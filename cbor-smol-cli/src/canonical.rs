@@ -0,0 +1,148 @@
+//! Re-encoding in the core deterministic encoding of RFC 8949 §4.2.1.
+
+use cbor_smol::ser::Encoder;
+use cbor_smol::token::{Decoder, Token};
+use cbor_smol::{validate, Error, ValidationOptions};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Re-encode a single CBOR item deterministically.
+///
+/// Integers, lengths, tags and floats use their shortest form, indefinite-length items are
+/// converted to definite-length items and map keys are sorted bytewise.  Duplicate map keys are
+/// rejected with [`Error::DeserializeUnsortedKeys`][].
+pub fn canonicalize(input: &[u8]) -> Result<Vec<u8>> {
+    validate(input, ValidationOptions::default())?;
+    let mut decoder = Decoder::new(input);
+    item(&mut decoder)
+}
+
+fn item(decoder: &mut Decoder) -> Result<Vec<u8>> {
+    let mut encoder = Encoder::new(Vec::new());
+    match decoder.next_token()? {
+        Token::UInt(value) => encoder.uint(value)?,
+        Token::NegInt(value) => encoder.negint(value)?,
+        Token::Bytes(bytes) => encoder.bytes(bytes)?,
+        Token::Text(text) => encoder.text(text)?,
+        Token::BytesIndefinite => {
+            let mut bytes = Vec::new();
+            while let Token::Bytes(chunk) = decoder.next_token()? {
+                bytes.extend_from_slice(chunk);
+            }
+            encoder.bytes(&bytes)?;
+        }
+        Token::TextIndefinite => {
+            let mut text = String::new();
+            while let Token::Text(chunk) = decoder.next_token()? {
+                text.push_str(chunk);
+            }
+            encoder.text(&text)?;
+        }
+        Token::Array(len) => {
+            let items = (0..len)
+                .map(|_| item(decoder))
+                .collect::<Result<Vec<_>>>()?;
+            array(&mut encoder, items)?;
+        }
+        Token::ArrayIndefinite => {
+            let mut items = Vec::new();
+            while !decoder.at_break() {
+                items.push(item(decoder)?);
+            }
+            decoder.next_token()?;
+            array(&mut encoder, items)?;
+        }
+        Token::Map(len) => {
+            let entries = (0..len)
+                .map(|_| Ok((item(decoder)?, item(decoder)?)))
+                .collect::<Result<Vec<_>>>()?;
+            map(&mut encoder, entries)?;
+        }
+        Token::MapIndefinite => {
+            let mut entries = Vec::new();
+            while !decoder.at_break() {
+                entries.push((item(decoder)?, item(decoder)?));
+            }
+            decoder.next_token()?;
+            map(&mut encoder, entries)?;
+        }
+        Token::Tag(tag) => {
+            encoder.tag(tag)?;
+            encoder.raw(&item(decoder)?)?;
+        }
+        Token::Bool(value) => encoder.bool(value)?,
+        Token::Null => encoder.null()?,
+        Token::Undefined => encoder.simple(23)?,
+        Token::Simple(value) => encoder.simple(value)?,
+        Token::Float(value) => encoder.float(value)?,
+        Token::Break => return Err(Error::DeserializeBadEncoding),
+    }
+    Ok(encoder.into_inner())
+}
+
+fn array(encoder: &mut Encoder<Vec<u8>>, items: Vec<Vec<u8>>) -> Result<()> {
+    encoder.array_header(items.len())?;
+    for item in items {
+        encoder.raw(&item)?;
+    }
+    Ok(())
+}
+
+fn map(encoder: &mut Encoder<Vec<u8>>, mut entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+    entries.sort();
+    if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err(Error::DeserializeUnsortedKeys);
+    }
+    encoder.map_header(entries.len())?;
+    for (key, value) in entries {
+        encoder.raw(&key)?;
+        encoder.raw(&value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonicalize;
+    use cbor_smol::diag::parse;
+    use cbor_smol::{validate, Error, ValidationOptions};
+
+    fn cbor(text: &str) -> Vec<u8> {
+        let mut buf = [0u8; 256];
+        parse(text, &mut buf).unwrap().to_vec()
+    }
+
+    #[test]
+    fn canonical() {
+        let deterministic = ValidationOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        for (input, expected) in [
+            (&[0x19, 0x00, 0x01][..], "1"),
+            (&[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0], "1.5"),
+            (&[0xfa, 0x7f, 0xc0, 0x00, 0x00], "NaN"),
+            (&[0x5f, 0x41, 0x01, 0x41, 0x02, 0xff], "h'0102'"),
+            (&[0x7f, 0x61, b'a', 0x61, b'b', 0xff], "\"ab\""),
+            (&[0x9f, 0x01, 0x9f, 0xff, 0xff], "[1, []]"),
+            (
+                &[0xbf, 0x61, b'a', 0x01, 0x18, 0x18, 0x02, 0x01, 0x03, 0xff],
+                "{1: 3, 24: 2, \"a\": 1}",
+            ),
+            (&[0xc2, 0x5f, 0x41, 0x01, 0xff], "2(h'01')"),
+        ] {
+            let output = canonicalize(input).unwrap();
+            assert_eq!(output, cbor(expected), "{expected}");
+            assert_eq!(validate(&output, deterministic), Ok(()));
+        }
+
+        assert_eq!(
+            canonicalize(&[0xa2, 0x01, 0x01, 0x01, 0x02]),
+            Err(Error::DeserializeUnsortedKeys)
+        );
+        assert_eq!(
+            canonicalize(&[0x82, 0x01]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+}
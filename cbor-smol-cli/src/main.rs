@@ -0,0 +1,220 @@
+//! Command-line tool to inspect and convert CBOR data.
+//!
+//! All commands read from a file, or from stdin if no file is given.  Decoding is done with
+//! cbor-smol itself, so the tool accepts and rejects the same data as the firmware.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Context, Result};
+use cbor_smol::diag::Diag;
+use cbor_smol::transcode::{cbor_to_json, cbor_to_json_pretty, json_to_cbor};
+use cbor_smol::{validate, ValidationOptions};
+use clap::{Args, Parser, Subcommand};
+
+mod canonical;
+
+#[derive(Parser)]
+#[command(name = "cbor-smol", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print CBOR data in diagnostic notation
+    Diag {
+        #[command(flatten)]
+        input: Input,
+        /// Put the items of arrays and maps on separate lines
+        #[arg(short, long)]
+        pretty: bool,
+    },
+    /// Convert CBOR data to JSON, or JSON to CBOR
+    ///
+    /// Byte strings are converted to base64url strings, tags are dropped and bignums are
+    /// converted to integers if they fit into 128 bits.
+    Json {
+        #[command(flatten)]
+        input: Input,
+        /// Convert JSON to CBOR instead
+        #[arg(short, long)]
+        reverse: bool,
+        /// Indent the JSON output
+        #[arg(short, long, conflicts_with = "reverse")]
+        pretty: bool,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Check that the input is exactly one well-formed CBOR item
+    Validate {
+        #[command(flatten)]
+        input: Input,
+        /// Require the deterministic encoding of RFC 8949 §4.2.1
+        #[arg(short, long)]
+        deterministic: bool,
+        /// Reject indefinite-length items
+        #[arg(long)]
+        no_indefinite: bool,
+        /// Do not check the content of well-known tags
+        #[arg(long)]
+        no_tag_checks: bool,
+        /// The maximum nesting depth
+        #[arg(long, default_value_t = cbor_smol::de::MAX_DEPTH)]
+        max_depth: usize,
+    },
+    /// Re-encode a CBOR item in the deterministic encoding of RFC 8949 §4.2.1
+    Canonicalize {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Print the input as hex, or convert hex to binary
+    Hex {
+        #[command(flatten)]
+        input: Input,
+        /// Convert hex to binary instead, ignoring whitespace
+        #[arg(short, long)]
+        decode: bool,
+    },
+}
+
+#[derive(Args)]
+struct Input {
+    /// The input file, stdin if missing or `-`
+    file: Option<PathBuf>,
+}
+
+impl Input {
+    fn read(&self) -> Result<Vec<u8>> {
+        match &self.file {
+            Some(path) if path.as_os_str() != "-" => {
+                fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+            }
+            _ => {
+                let mut data = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut data)
+                    .context("failed to read stdin")?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+#[derive(Args)]
+struct Output {
+    /// Print binary output as hex
+    #[arg(short = 'x', long)]
+    hex: bool,
+}
+
+impl Output {
+    fn write(&self, data: &[u8]) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        if self.hex {
+            writeln!(stdout, "{}", hex::encode(data))?;
+        } else {
+            stdout.write_all(data)?;
+        }
+        Ok(stdout.flush()?)
+    }
+}
+
+/// Convert a cbor-smol error, which does not implement `std::error::Error`.
+fn cbor_error(err: cbor_smol::Error) -> anyhow::Error {
+    anyhow!("{err}")
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Diag { input, pretty } => {
+            let data = input.read()?;
+            let mut out = String::new();
+            Diag::new(&data)
+                .pretty(pretty)
+                .write_to(&mut out)
+                .map_err(cbor_error)
+                .with_context(|| format!("malformed CBOR after `{out}`"))?;
+            println!("{out}");
+        }
+        Command::Json {
+            input,
+            reverse,
+            pretty,
+            output,
+        } => {
+            let data = input.read()?;
+            if reverse {
+                let mut cbor = Vec::new();
+                json_to_cbor(&data, &mut cbor).context("invalid JSON")?;
+                output.write(&cbor)?;
+            } else {
+                // the input may be a CBOR Sequence, print one JSON value per line
+                let mut stdout = io::stdout().lock();
+                let mut remaining = &data[..];
+                while !remaining.is_empty() {
+                    let len =
+                        cbor_smol::item_len(remaining).map_err(|_| anyhow!("malformed CBOR"))?;
+                    let item = &remaining[..len];
+                    if pretty {
+                        cbor_to_json_pretty(item, &mut stdout)?;
+                    } else {
+                        cbor_to_json(item, &mut stdout)?;
+                    }
+                    writeln!(stdout)?;
+                    remaining = &remaining[len..];
+                }
+            }
+        }
+        Command::Validate {
+            input,
+            deterministic,
+            no_indefinite,
+            no_tag_checks,
+            max_depth,
+        } => {
+            let data = input.read()?;
+            let options = ValidationOptions {
+                max_depth,
+                allow_indefinite: !no_indefinite,
+                check_tags: !no_tag_checks,
+                deterministic,
+            };
+            validate(&data, options).map_err(cbor_error)?;
+            println!("valid");
+        }
+        Command::Canonicalize { input, output } => {
+            let data = input.read()?;
+            output.write(&canonical::canonicalize(&data).map_err(cbor_error)?)?;
+        }
+        Command::Hex { input, decode } => {
+            let data = input.read()?;
+            if decode {
+                let digits: Vec<u8> = data
+                    .into_iter()
+                    .filter(|c| !c.is_ascii_whitespace())
+                    .collect();
+                let bytes = hex::decode(digits).context("invalid hex")?;
+                io::stdout().lock().write_all(&bytes)?;
+            } else {
+                println!("{}", hex::encode(data));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
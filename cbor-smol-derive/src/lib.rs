@@ -0,0 +1,192 @@
+//! Derive macros for [cbor-smol](https://docs.rs/cbor-smol).
+//!
+//! Use these through the `derive` feature of `cbor-smol` rather than depending on this crate
+//! directly.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, parse_quote, spanned::Spanned, Attribute, Data,
+    DeriveInput, Error, Fields, GenericParam, Generics, LitStr, Result, Token,
+};
+
+/// Derive `cbor_smol::CborMaxSize` for a struct or enum.
+///
+/// The computed size follows the cbor-smol serializer: structs are maps with string keys, tuple
+/// structs are arrays, newtype structs are transparent and enum variants are encoded as their
+/// index, possibly in an array together with the variant data.
+///
+/// The `rename`, `skip` and `skip_serializing` serde attributes are taken into account.  Serde
+/// attributes that change the structure of the encoding, such as `flatten` or `tag`, are
+/// rejected.
+#[proc_macro_derive(CborMaxSize, attributes(serde))]
+pub fn derive_max_size(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream> {
+    check_container_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let generics = add_trait_bounds(input.generics);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let max_size = match &input.data {
+        Data::Struct(data) => {
+            let sizes = field_sizes(&data.fields)?;
+            let len = sizes.len();
+            match &data.fields {
+                // null
+                Fields::Unit => quote!(1),
+                // newtype struct
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote!(#(#sizes)*),
+                _ => quote!(::cbor_smol::max_size::header_size(#len) #(+ #sizes)*),
+            }
+        }
+        Data::Enum(data) => {
+            let mut max_size = quote!(0);
+            for (index, variant) in data.variants.iter().enumerate() {
+                if field_attrs(&variant.attrs)?.skip {
+                    continue;
+                }
+                let index = quote!(::cbor_smol::max_size::header_size(#index));
+                let sizes = field_sizes(&variant.fields)?;
+                let len = sizes.len();
+                let size = match &variant.fields {
+                    Fields::Unit => index,
+                    // [index, value]
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        quote!(1 + #index #(+ #sizes)*)
+                    }
+                    // [index, values...]
+                    Fields::Unnamed(_) => quote!(
+                        ::cbor_smol::max_size::header_size(#len + 1) + #index #(+ #sizes)*
+                    ),
+                    // [index, {fields}]
+                    Fields::Named(_) => quote!(
+                        1 + #index + ::cbor_smol::max_size::header_size(#len) #(+ #sizes)*
+                    ),
+                };
+                max_size = quote!(::cbor_smol::max_size::max(#max_size, #size));
+            }
+            max_size
+        }
+        Data::Union(data) => {
+            return Err(Error::new(
+                data.union_token.span(),
+                "CborMaxSize cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::cbor_smol::CborMaxSize for #name #ty_generics #where_clause {
+            const MAX_SIZE: usize = #max_size;
+        }
+    })
+}
+
+/// The maximum sizes of the serialized fields, including the keys of named fields.
+fn field_sizes(fields: &Fields) -> Result<Vec<TokenStream>> {
+    let mut sizes = Vec::new();
+    for field in fields {
+        let attrs = field_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ty = &field.ty;
+        let value = quote_spanned!(ty.span()=> <#ty as ::cbor_smol::CborMaxSize>::MAX_SIZE);
+        match &field.ident {
+            Some(ident) => {
+                let key = attrs.rename.unwrap_or_else(|| {
+                    let ident = ident.to_string();
+                    let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+                    LitStr::new(ident, Span::call_site())
+                });
+                sizes.push(quote!(::cbor_smol::max_size::str_size(#key) + #value));
+            }
+            None => sizes.push(value),
+        }
+    }
+    Ok(sizes)
+}
+
+fn add_trait_bounds(mut generics: Generics) -> Generics {
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(::cbor_smol::CborMaxSize));
+        }
+    }
+    generics
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<LitStr>,
+    skip: bool,
+}
+
+fn field_attrs(attrs: &[Attribute]) -> Result<FieldAttrs> {
+    let mut field_attrs = FieldAttrs::default();
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(Token![=]) {
+                    field_attrs.rename = Some(meta.value()?.parse()?);
+                } else {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("serialize") {
+                            field_attrs.rename = Some(meta.value()?.parse()?);
+                            Ok(())
+                        } else {
+                            skip_meta(meta)
+                        }
+                    })?;
+                }
+                Ok(())
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                field_attrs.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("flatten") {
+                Err(meta.error("CborMaxSize does not support flattened fields"))
+            } else {
+                skip_meta(meta)
+            }
+        })?;
+    }
+    Ok(field_attrs)
+}
+
+fn check_container_attrs(attrs: &[Attribute]) -> Result<()> {
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            for unsupported in ["tag", "content", "untagged", "rename_all", "transparent"] {
+                if meta.path.is_ident(unsupported) {
+                    return Err(meta.error(format_args!(
+                        "CborMaxSize does not support the serde attribute `{unsupported}`"
+                    )));
+                }
+            }
+            skip_meta(meta)
+        })?;
+    }
+    Ok(())
+}
+
+/// Consume the value of a serde attribute that does not affect the serialized size.
+fn skip_meta(meta: ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(skip_meta)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use cbor_smol::{cbor_serialize, CborMaxSize};
+use heapless_v0_8::{String, Vec};
+use serde::Serialize;
+
+fn assert_tight<T: CborMaxSize + Serialize>(value: &T) {
+    let mut buf = [0u8; 4096];
+    let ser = cbor_serialize(value, &mut buf).unwrap();
+    assert_eq!(ser.len(), T::MAX_SIZE);
+}
+
+#[derive(CborMaxSize, Serialize)]
+struct Unit;
+
+#[derive(CborMaxSize, Serialize)]
+struct Newtype(u32);
+
+#[derive(CborMaxSize, Serialize)]
+struct Tuple(u8, i64);
+
+#[derive(CborMaxSize, Serialize)]
+struct Struct<T> {
+    counter: u32,
+    #[serde(rename = "rk")]
+    resident_key: Option<bool>,
+    #[serde(skip)]
+    #[allow(unused)]
+    skipped: u64,
+    name: String<64>,
+    values: Vec<T, 30>,
+}
+
+#[derive(CborMaxSize, Serialize)]
+enum Enum {
+    A,
+    B(u64),
+    C(u8, u16, Tuple),
+    D { x: i32, y: Struct<u8> },
+}
+
+#[test]
+fn derive_struct() {
+    assert_tight(&Unit);
+    assert_tight(&Newtype(u32::MAX));
+    assert_tight(&Tuple(u8::MAX, i64::MIN));
+
+    let mut name = String::new();
+    name.push_str(&"x".repeat(64)).unwrap();
+    let value = Struct {
+        counter: u32::MAX,
+        resident_key: Some(true),
+        skipped: 0,
+        name,
+        values: Vec::from_slice(&[u16::MAX; 30]).unwrap(),
+    };
+    assert_tight(&value);
+    assert_eq!(
+        Struct::<u16>::MAX_SIZE,
+        1 + (8 + 5) + (3 + 1) + (5 + 2 + 64) + (7 + 2 + 30 * 3)
+    );
+}
+
+#[test]
+fn derive_enum() {
+    let mut name = String::new();
+    name.push_str(&"x".repeat(64)).unwrap();
+    let value = Enum::D {
+        x: i32::MIN,
+        y: Struct {
+            counter: u32::MAX,
+            resident_key: Some(false),
+            skipped: 0,
+            name,
+            values: Vec::from_slice(&[u8::MAX; 30]).unwrap(),
+        },
+    };
+    assert_tight(&value);
+
+    let mut buf = [0u8; Enum::MAX_SIZE];
+    for value in [
+        Enum::A,
+        Enum::B(u64::MAX),
+        Enum::C(u8::MAX, u16::MAX, Tuple(u8::MAX, i64::MIN)),
+    ] {
+        assert!(cbor_serialize(&value, &mut buf).is_ok());
+    }
+    assert_eq!(
+        <[Enum; 2]>::MAX_SIZE,
+        1 + 2 * <Enum as CborMaxSize>::MAX_SIZE
+    );
+}
@@ -0,0 +1,230 @@
+//! Fixed-size byte arrays encoded as CBOR byte strings.
+//!
+//! serde encodes `[u8; N]` as an array of integers, which takes up to twice the space.  The
+//! functions in this module can be used with `#[serde(with = "cbor_smol::bytes")]` on `[u8; N]`
+//! fields to encode them as byte strings instead, or the array can be wrapped in a
+//! [`ByteArray`][]:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Credential {
+//!     #[serde(with = "cbor_smol::bytes")]
+//!     id: [u8; 16],
+//!     key: cbor_smol::bytes::ByteArray<32>,
+//! }
+//!
+//! let credential = Credential {
+//!     id: [0x11; 16],
+//!     key: [0x22; 32].into(),
+//! };
+//! let mut buf = [0u8; 64];
+//! let ser = cbor_smol::cbor_serialize(&credential, &mut buf).unwrap();
+//! assert_eq!(ser.len(), 1 + 3 + 1 + 16 + 4 + 2 + 32);
+//! assert_eq!(cbor_smol::cbor_deserialize::<Credential>(ser), Ok(credential));
+//! ```
+//!
+//! Both deserialize from a byte string or an array of integers.  If the length is not exactly
+//! `N`, the cbor-smol deserializer reports [`Error::DeserializeBadLength`][].
+//!
+//! [`Error::DeserializeBadLength`]: crate::Error::DeserializeBadLength
+
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+/// The newtype struct name that the cbor-smol deserializer recognizes for byte arrays, to tell
+/// a byte string of the wrong length from one that exceeds a container's capacity.
+pub(crate) const BYTE_ARRAY_NAME: &str = "\0cbor_smol::ByteArray";
+
+/// Serialize a byte array as a byte string.
+pub fn serialize<S: Serializer, const N: usize>(
+    bytes: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+/// Deserialize a byte array from a byte string or an array of integers of length `N`.
+pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    deserializer.deserialize_newtype_struct(BYTE_ARRAY_NAME, ByteArrayVisitor::<N>)
+}
+
+struct ByteArrayVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for ByteArrayVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a byte string of length {}", N)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<[u8; N], E> {
+        v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[u8; N], A::Error> {
+        let mut array = [0; N];
+        for (i, byte) in array.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        let mut len = N;
+        while seq.next_element::<de::IgnoredAny>()?.is_some() {
+            len += 1;
+        }
+        if len != N {
+            return Err(de::Error::invalid_length(len, &self));
+        }
+        Ok(array)
+    }
+}
+
+/// A byte array that is encoded as a byte string.
+///
+/// See the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteArray<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> ByteArray<N> {
+    /// Wrap a byte array.
+    pub const fn new(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+
+    /// Unwrap the byte array.
+    pub const fn into_inner(self) -> [u8; N] {
+        self.0
+    }
+}
+
+impl<const N: usize> Default for ByteArray<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for ByteArray<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> From<ByteArray<N>> for [u8; N] {
+    fn from(bytes: ByteArray<N>) -> Self {
+        bytes.0
+    }
+}
+
+impl<const N: usize> Deref for ByteArray<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for ByteArray<N> {
+    fn deref_mut(&mut self) -> &mut [u8; N] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for ByteArray<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> AsMut<[u8]> for ByteArray<N> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> Serialize for ByteArray<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for ByteArray<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ByteArray;
+    use crate::{cbor_deserialize, cbor_serialize, CborMaxSize, Error};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Key {
+        #[serde(with = "super")]
+        id: [u8; 4],
+        data: ByteArray<2>,
+    }
+
+    #[test]
+    fn byte_array() {
+        let mut buf = [0u8; 32];
+        let key = Key {
+            id: [1, 2, 3, 4],
+            data: ByteArray::new([5, 6]),
+        };
+        let ser = cbor_serialize(&key, &mut buf).unwrap();
+        assert_eq!(
+            ser,
+            [0xa2, 0x62, b'i', b'd', 0x44, 1, 2, 3, 4, 0x64, b'd', b'a', b't', b'a', 0x42, 5, 6]
+        );
+        assert_eq!(cbor_deserialize::<Key>(ser), Ok(key));
+        assert_eq!(
+            cbor_serialize(&ByteArray([7; 24]), &mut buf).unwrap().len(),
+            ByteArray::<24>::MAX_SIZE
+        );
+
+        // arrays of integers
+        assert_eq!(
+            cbor_deserialize::<ByteArray<2>>(&[0x82, 0x18, 0xff, 0x00]),
+            Ok(ByteArray([0xff, 0]))
+        );
+        assert_eq!(
+            cbor_deserialize::<ByteArray<2>>(&[0x82, 0x19, 0x01, 0x00, 0x00]),
+            Err(Error::DeserializeBadU8)
+        );
+
+        // exact length
+        for input in [
+            &[0x41, 1][..],
+            &[0x43, 1, 2, 3],
+            &[0x81, 1],
+            &[0x83, 1, 2, 3],
+        ] {
+            assert_eq!(
+                cbor_deserialize::<ByteArray<2>>(input),
+                Err(Error::DeserializeBadLength)
+            );
+        }
+        assert_eq!(cbor_deserialize::<ByteArray<0>>(&[0x40]), Ok(ByteArray([])));
+        assert_eq!(
+            cbor_deserialize::<ByteArray<2>>(&[0x62, b'a', b'b']),
+            Err(Error::DeserializeBadMajor)
+        );
+    }
+}
@@ -3,7 +3,11 @@ use serde::Deserialize;
 use serde::de::IntoDeserializer;
 
 use super::error::{Error, Result};
+use crate::bytes::BYTE_ARRAY_NAME;
 use crate::consts::*;
+use crate::raw::RAW_CBOR_NAME;
+use crate::simple::{SIMPLE_NAME, UNDEFINED_NAME};
+use crate::token::f16_to_f64;
 
 /// Deserialize a message of type `T` from a byte slice. The unused portion (if any)
 /// of the byte slice is returned for further usage
@@ -16,6 +20,20 @@ where
     Ok(t)
 }
 
+/// Deserialize a message of type `T` from a byte slice into an existing value.
+///
+/// This uses [`Deserialize::deserialize_in_place`][], so large values are refilled where they
+/// are instead of being built on the stack and moved.  Arrays and tuples support this out of the
+/// box.  For derived types, the `deserialize_in_place` feature of `serde_derive` must be enabled;
+/// otherwise, serde falls back to deserializing a new value and assigning it.
+pub fn from_bytes_in_place<'a, T>(s: &'a [u8], place: &mut T) -> Result<()>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+    T::deserialize_in_place(&mut deserializer, place)
+}
+
 /// Deserialize a message of type `T` from a byte slice. The unused portion (if any)
 /// of the byte slice is returned for further usage
 pub fn take_from_bytes<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
@@ -27,6 +45,82 @@ where
     Ok((t, deserializer.input))
 }
 
+/// The maximum nesting depth of arrays, maps and tags when skipping over items.
+pub const MAX_DEPTH: usize = 32;
+
+/// Resource limits for a [`Deserializer`][], checked before the data is processed.
+///
+/// A breach is reported as [`Error::DeserializeLimitExceeded`][].  The default has no limits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// The maximum number of items in an array or entries in a map.
+    pub max_collection_len: usize,
+    /// The maximum length of a byte or text string in bytes, including all chunks of an
+    /// indefinite-length string.
+    pub max_string_len: usize,
+    /// The maximum number of array items and map entries in total.
+    pub max_items: usize,
+    /// The maximum size of the input in bytes.
+    pub max_input_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_collection_len: usize::MAX,
+            max_string_len: usize::MAX,
+            max_items: usize::MAX,
+            max_input_len: usize::MAX,
+        }
+    }
+}
+
+/// Deserialize a message of type `T` from a byte slice, enforcing `limits`.
+pub fn from_bytes_with_limits<'a, T>(s: &'a [u8], limits: Limits) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::with_limits(s, limits)?;
+    T::deserialize(&mut deserializer)
+}
+
+/// Deserialize a message of type `T` from a byte slice, accepting integers, lengths and tags
+/// that are not in their shortest form.
+///
+/// This also covers items that are skipped or captured as [`RawCbor`](crate::RawCbor).
+/// [`item_len`][], [`RawCbor::from_slice`](crate::RawCbor::from_slice) and
+/// [`Sequence`](crate::sequence::Sequence) always require the shortest form.  See
+/// [`Deserializer::minimal`][].
+pub fn from_bytes_lenient<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s).minimal(false);
+    T::deserialize(&mut deserializer)
+}
+
+/// The reason why [`item_len`][] could not determine the length of an item.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ItemLenError {
+    /// The buffer holds the beginning of an item, more bytes are needed.
+    Incomplete,
+    /// The buffer does not start with a well-formed item.
+    Invalid(Error),
+}
+
+/// Determine the length of the CBOR item at the start of a byte slice.
+///
+/// This walks the structure of the item without decoding it, so it can be used to check
+/// whether a buffer holds a complete item before dispatching it.
+pub fn item_len(s: &[u8]) -> Result<usize, ItemLenError> {
+    let mut deserializer = Deserializer::from_bytes(s);
+    match deserializer.ignore() {
+        Ok(()) => Ok(s.len() - deserializer.input.len()),
+        Err(Error::DeserializeUnexpectedEnd) => Err(ItemLenError::Incomplete),
+        Err(err) => Err(ItemLenError::Invalid(err)),
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 // TODO: remove these allowances again later
@@ -42,12 +136,62 @@ pub struct Deserializer<'de> {
     // This string starts with the input data and characters are truncated off
     // the beginning as data is parsed.
     pub(crate) input: &'de [u8],
+    // remaining nesting depth for `deserialize_any`
+    depth: usize,
+    limits: Limits,
+    // array items and map entries seen so far, for `Limits::max_items`
+    items: usize,
+    // reject integers, lengths and tags that are not in their shortest form
+    minimal: bool,
+    // reject array items and map entries that the visitor does not consume
+    reject_trailing_items: bool,
 }
 
 impl<'de> Deserializer<'de> {
     /// Obtain a Deserializer from a slice of bytes
     pub fn from_bytes(input: &'de [u8]) -> Self {
-        Deserializer { input }
+        Deserializer {
+            input,
+            depth: MAX_DEPTH,
+            limits: Limits::default(),
+            items: 0,
+            minimal: true,
+            reject_trailing_items: false,
+        }
+    }
+
+    /// Obtain a Deserializer from a slice of bytes that enforces `limits`.
+    pub fn with_limits(input: &'de [u8], limits: Limits) -> Result<Self> {
+        if input.len() > limits.max_input_len {
+            return Err(Error::DeserializeLimitExceeded);
+        }
+        Ok(Deserializer {
+            limits,
+            ..Self::from_bytes(input)
+        })
+    }
+
+    /// Only accept integers, lengths and tags in their shortest form, and bignums that do not
+    /// fit into a plain integer (default).
+    ///
+    /// A lenient deserializer accepts any width that can hold the value, such as `0x19 0x00
+    /// 0x05` for 5.  Two-byte simple values below 32 are malformed and rejected either way.
+    ///
+    /// The setting applies to every item this deserializer reads, including skipped items and
+    /// [`RawCbor`](crate::RawCbor).  [`item_len`][], [`RawCbor::from_slice`](crate::RawCbor::from_slice)
+    /// and [`Sequence`](crate::sequence::Sequence) create their own strict deserializer.
+    pub fn minimal(mut self, minimal: bool) -> Self {
+        self.minimal = minimal;
+        self
+    }
+
+    /// Return [`Error::DeserializeTrailingItems`][] if an array or map has more items than the
+    /// deserialized type consumes, such as a third item for a pair.
+    ///
+    /// By default, these items are skipped.
+    pub fn reject_trailing_items(mut self, reject: bool) -> Self {
+        self.reject_trailing_items = reject;
+        self
     }
 }
 
@@ -101,6 +245,10 @@ impl<'de> Deserializer<'de> {
 
     // TODO: name something like "one-byte-integer"
     fn raw_deserialize_u8(&mut self, major: u8) -> Result<u8> {
+        if !self.minimal {
+            let number = self.raw_deserialize_u64(major)?;
+            return u8::try_from(number).map_err(|_| Error::DeserializeBadU8);
+        }
         let additional = self.expect_major(major)?;
 
         match additional {
@@ -123,6 +271,10 @@ impl<'de> Deserializer<'de> {
     }
 
     fn raw_deserialize_u32(&mut self, major: u8) -> Result<u32> {
+        if !self.minimal {
+            let number = self.raw_deserialize_u64(major)?;
+            return u32::try_from(number).map_err(|_| Error::DeserializeBadU32);
+        }
         let additional = self.expect_major(major)?;
 
         match additional {
@@ -156,13 +308,14 @@ impl<'de> Deserializer<'de> {
             _ => Err(Error::DeserializeBadU32),
         }
     }
+
     fn raw_deserialize_u64(&mut self, major: u8) -> Result<u64> {
         let additional = self.expect_major(major)?;
 
         match additional {
             byte @ 0..=23 => Ok(byte as u64),
             24 => match self.try_take_n(1)?[0] {
-                0..=23 => Err(Error::DeserializeNonMinimal),
+                0..=23 if self.minimal => Err(Error::DeserializeNonMinimal),
                 byte => Ok(byte as u64),
             },
             25 => {
@@ -172,7 +325,7 @@ impl<'de> Deserializer<'de> {
                         .map_err(|_| Error::InexistentSliceToArrayError)?,
                 );
                 match unsigned {
-                    0..=255 => Err(Error::DeserializeNonMinimal),
+                    0..=255 if self.minimal => Err(Error::DeserializeNonMinimal),
                     unsigned => Ok(unsigned as u64),
                 }
             }
@@ -183,7 +336,7 @@ impl<'de> Deserializer<'de> {
                         .map_err(|_| Error::InexistentSliceToArrayError)?,
                 );
                 match unsigned {
-                    0..=65535 => Err(Error::DeserializeNonMinimal),
+                    0..=65535 if self.minimal => Err(Error::DeserializeNonMinimal),
                     unsigned => Ok(unsigned as u64),
                 }
             }
@@ -194,7 +347,7 @@ impl<'de> Deserializer<'de> {
                         .map_err(|_| Error::InexistentSliceToArrayError)?,
                 );
                 match unsigned {
-                    0..=0xFFFFFFFF => Err(Error::DeserializeNonMinimal),
+                    0..=0xFFFFFFFF if self.minimal => Err(Error::DeserializeNonMinimal),
                     unsigned => Ok(unsigned),
                 }
             }
@@ -202,80 +355,448 @@ impl<'de> Deserializer<'de> {
         }
     }
 
+    /// Read a plain integer or a bignum (tag 2 or 3) of up to 128 bits.
+    ///
+    /// Returns whether the integer is negative, and its magnitude: the value itself, or
+    /// `-1 - value` for negative integers.  Unless the deserializer is lenient, bignums that are
+    /// not minimal or would fit into a plain integer are rejected.  Magnitudes beyond 128 bits
+    /// are reported as `too_large`.
+    fn raw_deserialize_u128(&mut self, too_large: Error) -> Result<(bool, u128)> {
+        let major = self.peek_major()?;
+        if major != MAJOR_TAG {
+            let negative = major == MAJOR_NEGINT;
+            return Ok((negative, self.raw_deserialize_u64(major)?.into()));
+        }
+        let negative = match self.raw_deserialize_u64(MAJOR_TAG)? {
+            TAG_POSITIVE_BIGNUM => false,
+            TAG_NEGATIVE_BIGNUM => true,
+            _ => return Err(Error::DeserializeBadTag),
+        };
+        if self.peek_major()? != MAJOR_BYTES || self.is_indefinite()? {
+            return Err(Error::DeserializeBadTag);
+        }
+        let length = self.string_len(MAJOR_BYTES)?;
+        let mut bytes = self.try_take_n(length)?;
+        if self.minimal {
+            if bytes.len() <= 16 && (bytes.len() <= 8 || bytes[0] == 0) {
+                return Err(Error::DeserializeNonMinimal);
+            }
+        } else {
+            while let [0, rest @ ..] = bytes {
+                bytes = rest;
+            }
+        }
+        if bytes.len() > 16 {
+            return Err(too_large);
+        }
+        let magnitude = bytes
+            .iter()
+            .fold(0, |acc, byte| acc << 8 | u128::from(*byte));
+        Ok((negative, magnitude))
+    }
+
+    /// Visit the byte string of a bignum as `u128` or `i128` if the value fits, and as bytes
+    /// otherwise.
+    ///
+    /// Unlike `raw_deserialize_u128`, this accepts leading zeros, as any bignum is well-formed.
+    fn visit_bignum<V: Visitor<'de>>(&mut self, negative: bool, visitor: V) -> Result<V::Value> {
+        let length = self.string_len(MAJOR_BYTES)?;
+        let bytes = self.try_take_n(length)?;
+        let mut digits = bytes;
+        while let [0, rest @ ..] = digits {
+            digits = rest;
+        }
+        if digits.len() <= 16 {
+            let magnitude = digits
+                .iter()
+                .fold(0, |acc, byte| acc << 8 | u128::from(*byte));
+            match (negative, i128::try_from(magnitude)) {
+                (false, _) => return visitor.visit_u128(magnitude),
+                (true, Ok(magnitude)) => return visitor.visit_i128(-1 - magnitude),
+                (true, Err(_)) => {}
+            }
+        }
+        visitor.visit_borrowed_bytes(bytes)
+    }
+
+    /// Count `count` array items or map entries towards `Limits::max_items`.
+    fn count_items(&mut self, count: usize) -> Result<()> {
+        self.items = self.items.saturating_add(count);
+        if self.items > self.limits.max_items {
+            return Err(Error::DeserializeLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Read the length of a definite-length array or map and check it against the limits.
+    ///
+    /// Every item takes at least one byte, so a length that the remaining input cannot hold is
+    /// rejected up front instead of after reading the items that are there.
+    fn collection_len(&mut self, major: u8) -> Result<usize> {
+        let len = self.raw_deserialize_u32(major)? as usize;
+        if len > self.limits.max_collection_len {
+            return Err(Error::DeserializeLimitExceeded);
+        }
+        self.count_items(len)?;
+        let min_size = if major == MAJOR_MAP {
+            len.saturating_mul(2)
+        } else {
+            len
+        };
+        if min_size > self.input.len() {
+            return Err(Error::DeserializeUnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    /// Read the length of a definite-length byte or text string and check it against the
+    /// limits.
+    fn string_len(&mut self, major: u8) -> Result<usize> {
+        let len = self.raw_deserialize_u32(major)? as usize;
+        if len > self.limits.max_string_len {
+            return Err(Error::DeserializeLimitExceeded);
+        }
+        Ok(len)
+    }
+
     fn ignore_int(&mut self, major: u8) -> Result<()> {
-        let additional = self.expect_major(major)?;
-        match additional {
-            0..=23 => {}
-            24 => {
-                self.try_take_n(1)?;
+        if self.peek()? & ((1 << MAJOR_OFFSET) - 1) > 27 {
+            self.expect_major(major)?;
+            return Err(Error::DeserializeBadU16);
+        }
+        self.raw_deserialize_u64(major).map(|_| ())
+    }
+
+    fn is_indefinite(&mut self) -> Result<bool> {
+        Ok(self.peek()? & ((1 << MAJOR_OFFSET) - 1) == 31)
+    }
+
+    /// Consume the break stop code if it is next.
+    fn consume_break(&mut self) -> Result<bool> {
+        if self.peek()? == VALUE_BREAK {
+            self.consume()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn ignore_bytes(&mut self, major: u8) -> Result<()> {
+        if self.is_indefinite()? {
+            self.consume()?;
+            // definite-length chunks of the same major type
+            let mut total = 0usize;
+            while !self.consume_break()? {
+                let length = self.string_len(major)?;
+                total = total.saturating_add(length);
+                if total > self.limits.max_string_len {
+                    return Err(Error::DeserializeLimitExceeded);
+                }
+                self.try_take_n(length)?;
+            }
+            return Ok(());
+        }
+        let length = self.string_len(major)?;
+        self.try_take_n(length)?;
+        Ok(())
+    }
+
+    /// Read a simple value, rejecting the reserved values 24 to 31 and floats.
+    fn raw_deserialize_simple(&mut self) -> Result<u8> {
+        match self.expect_major(MAJOR_SIMPLE)? {
+            value @ 0..=23 => Ok(value),
+            24 => match self.try_take_n(1)?[0] {
+                0..=31 => Err(Error::DeserializeBadSimple),
+                value => Ok(value),
+            },
+            _ => Err(Error::DeserializeBadSimple),
+        }
+    }
+
+    /// Skip a simple value or float.  Reserved values and a misplaced break are malformed.
+    fn ignore_simple(&mut self) -> Result<()> {
+        match self.peek()? & ((1 << MAJOR_OFFSET) - 1) {
+            0..=24 => {
+                self.raw_deserialize_simple().map_err(|err| match err {
+                    Error::DeserializeBadSimple => Error::DeserializeBadEncoding,
+                    err => err,
+                })?;
             }
             25 => {
-                self.try_take_n(2)?;
+                self.try_take_n(3)?;
             }
             26 => {
-                self.try_take_n(4)?;
+                self.try_take_n(5)?;
             }
             27 => {
-                self.try_take_n(8)?;
+                self.try_take_n(9)?;
             }
-            _ => return Err(Error::DeserializeBadU16),
+            _ => return Err(Error::DeserializeBadEncoding),
         };
         Ok(())
     }
 
-    fn ignore_bytes(&mut self, major: u8) -> Result<()> {
-        let length = self.raw_deserialize_u32(major)? as usize;
-        self.try_take_n(length)?;
-        Ok(())
+    /// Skip one complete item, nested at most [`MAX_DEPTH`][] levels deep.
+    ///
+    /// This does not recurse: the open arrays, maps and tags are tracked in a fixed-size stack,
+    /// so the stack usage does not depend on the input.
+    pub(crate) fn ignore(&mut self) -> Result<()> {
+        let mut stack = [Open::default(); MAX_DEPTH];
+        // the number of open items, which is the nesting level of the next item
+        let mut open = 0;
+        loop {
+            let major = self.peek_major()?;
+            match major {
+                MAJOR_POSINT | MAJOR_NEGINT => self.ignore_int(major)?,
+                MAJOR_BYTES | MAJOR_STR => self.ignore_bytes(major)?,
+                MAJOR_SIMPLE => self.ignore_simple()?,
+                _ if open >= MAX_DEPTH => return Err(Error::DeserializeDepthLimitExceeded),
+                _ => {
+                    stack[open] = self.open_item(major)?;
+                    open += 1;
+                }
+            }
+
+            // close the items that are complete, then continue with the next item
+            loop {
+                let Some(last) = open.checked_sub(1) else {
+                    return Ok(());
+                };
+                if self.next_open_item(&mut stack[last])? {
+                    break;
+                }
+                open -= 1;
+            }
+        }
     }
 
-    fn ignore_array(&mut self, major: u8, mult: usize) -> Result<()> {
-        let length = self.raw_deserialize_u32(major)? as usize;
-        let Some(real_length) = length.checked_mul(mult) else {
-            return Err(Error::InexistentSliceToArrayError);
+    /// Consume the header of an array, map or tag for `ignore`.
+    fn open_item(&mut self, major: u8) -> Result<Open> {
+        if major == MAJOR_TAG {
+            // the tag content is the only item
+            self.ignore_int(MAJOR_TAG)?;
+            return Ok(Open {
+                items: 1,
+                indefinite: false,
+                map: false,
+            });
+        }
+        let map = major == MAJOR_MAP;
+        let indefinite = self.is_indefinite()?;
+        let items = if indefinite {
+            self.consume()?;
+            0
+        } else {
+            let len = self.collection_len(major)?;
+            // only inputs of several GiB can hold more items
+            u32::try_from(if map { len * 2 } else { len })
+                .map_err(|_| Error::DeserializeLimitExceeded)?
         };
-        for _ in 0..real_length {
+        Ok(Open {
+            items,
+            indefinite,
+            map,
+        })
+    }
+
+    /// Check whether an open array, map or tag has another item, and count it.
+    fn next_open_item(&mut self, open: &mut Open) -> Result<bool> {
+        if !open.indefinite {
+            let Some(items) = open.items.checked_sub(1) else {
+                return Ok(false);
+            };
+            open.items = items;
+            return Ok(true);
+        }
+        let value_next = open.map && open.items % 2 == 1;
+        if self.consume_break()? {
+            if value_next {
+                // a map key without a value
+                return Err(Error::DeserializeBadEncoding);
+            }
+            return Ok(false);
+        }
+        open.items = open
+            .items
+            .checked_add(1)
+            .ok_or(Error::DeserializeLimitExceeded)?;
+        if !value_next {
+            let len = if open.map {
+                open.items.div_ceil(2)
+            } else {
+                open.items
+            } as usize;
+            if len > self.limits.max_collection_len {
+                return Err(Error::DeserializeLimitExceeded);
+            }
+            self.count_items(1)?;
+        }
+        Ok(true)
+    }
+
+    /// Skip `count` items that the visitor did not consume, unless they are rejected.
+    ///
+    /// Visitors for fixed-size types such as tuples or (in-place) arrays stop after the expected
+    /// number of items.
+    fn skip_trailing_items(&mut self, count: usize) -> Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        if self.reject_trailing_items {
+            return Err(Error::DeserializeTrailingItems);
+        }
+        for _ in 0..count {
             self.ignore()?;
         }
         Ok(())
     }
 
-    fn ignore_float(&mut self) -> Result<()> {
-        let additional = self.expect_major(7)?;
-        match additional {
-            0..=23 => {}
-            24 => {
-                self.try_take_n(1)?;
+    /// Let the visitor deserialize the `len` items of an array.
+    fn visit_seq<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value> {
+        let mut access = SeqAccess {
+            deserializer: self,
+            len,
+            yielded: false,
+        };
+        let value = match visitor.visit_seq(&mut access) {
+            Err(Error::DeserializeBadLength) if access.yielded => {
+                return Err(capacity_exceeded(len));
             }
-            25 => {
-                self.try_take_n(2)?;
+            result => result?,
+        };
+        let remaining = access.len;
+        self.skip_trailing_items(remaining)?;
+        Ok(value)
+    }
+
+    /// Let the visitor deserialize an array, or a byte string as a sequence of `u8` elements.
+    fn visit_array<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        if self.peek_major()? != MAJOR_BYTES {
+            let len = self.collection_len(MAJOR_ARRAY)?;
+            return self.visit_seq(len, visitor);
+        }
+        let length = self.string_len(MAJOR_BYTES)?;
+        let mut access = ByteSeqAccess {
+            bytes: self.try_take_n(length)?,
+            yielded: false,
+        };
+        let value = match visitor.visit_seq(&mut access) {
+            Err(Error::DeserializeBadLength) if access.yielded => {
+                return Err(capacity_exceeded(length));
             }
-            26 => {
-                self.try_take_n(4)?;
+            result => result?,
+        };
+        if !access.bytes.is_empty() && self.reject_trailing_items {
+            return Err(Error::DeserializeTrailingItems);
+        }
+        Ok(value)
+    }
+
+    /// Let the visitor deserialize a byte string, or an array as a sequence of `u8` elements.
+    ///
+    /// Fixed-capacity containers such as `heapless_bytes::Bytes` report a byte string that
+    /// does not fit as an invalid length.  Unless the visitor needs an `exact` length, this is
+    /// reported as [`Error::DeserializeCapacityExceeded`].
+    fn visit_bytes<V: Visitor<'de>>(&mut self, visitor: V, exact: bool) -> Result<V::Value> {
+        match self.peek_major()? {
+            MAJOR_ARRAY => {
+                let len = self.collection_len(MAJOR_ARRAY)?;
+                self.visit_seq(len, visitor)
             }
-            27 => {
-                self.try_take_n(8)?;
+            MAJOR_BYTES => {
+                // major type 2: "byte string"
+                let length = self.string_len(MAJOR_BYTES)?;
+                let bytes: &'de [u8] = self.try_take_n(length)?;
+                match visitor.visit_borrowed_bytes(bytes) {
+                    Err(Error::DeserializeBadLength) if !exact => Err(capacity_exceeded(length)),
+                    result => result,
+                }
+            }
+            _ => Err(Error::DeserializeBadMajor),
+        }
+    }
+
+    /// Let the visitor deserialize the `len` entries of a map.
+    fn visit_map<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value> {
+        let mut access = MapAccess {
+            deserializer: self,
+            len,
+            yielded: false,
+        };
+        let value = match visitor.visit_map(&mut access) {
+            Err(Error::DeserializeBadLength) if access.yielded => {
+                return Err(capacity_exceeded(len));
             }
-            _ => return Err(Error::DeserializeBadMajor),
+            result => result?,
         };
-        Ok(())
+        let remaining = access.len;
+        self.skip_trailing_items(2 * remaining)?;
+        Ok(value)
     }
 
-    fn ignore(&mut self) -> Result<()> {
-        let major = self.peek_major()?;
-        match major {
-            MAJOR_POSINT | MAJOR_NEGINT => self.ignore_int(major)?,
-            MAJOR_BYTES | MAJOR_STR => self.ignore_bytes(major)?,
-            MAJOR_ARRAY => self.ignore_array(MAJOR_ARRAY, 1)?,
-            MAJOR_MAP => self.ignore_array(MAJOR_MAP, 2)?,
-            6 => {
-                self.ignore_int(6)?;
+    /// Let the visitor deserialize the items of an indefinite-length array or map, up to and
+    /// including the break stop code.
+    fn visit_indefinite<V: Visitor<'de>>(&mut self, major: u8, visitor: V) -> Result<V::Value> {
+        let mut access = IndefiniteAccess {
+            deserializer: self,
+            len: 0,
+            done: false,
+            yielded: false,
+        };
+        let result = if major == MAJOR_MAP {
+            visitor.visit_map(&mut access)
+        } else {
+            visitor.visit_seq(&mut access)
+        };
+        let value = match result {
+            Err(Error::DeserializeBadLength) if access.yielded => {
+                return Err(capacity_exceeded(access.len));
+            }
+            result => result?,
+        };
+        if !access.done {
+            while !self.consume_break()? {
+                if self.reject_trailing_items {
+                    return Err(Error::DeserializeTrailingItems);
+                }
                 self.ignore()?;
             }
-            MAJOR_FLOAT => self.ignore_float()?,
-            _ => return Err(Error::DeserializeBadMajor),
         }
-        Ok(())
+        Ok(value)
+    }
+
+    /// Run `f` one nesting level deeper, for data-driven recursion in `deserialize_any`.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.depth = self
+            .depth
+            .checked_sub(1)
+            .ok_or(Error::DeserializeDepthLimitExceeded)?;
+        let result = f(self);
+        self.depth += 1;
+        result
+    }
+
+    fn raw_deserialize_f64(&mut self) -> Result<f64> {
+        let value = match self.expect_major(MAJOR_FLOAT)? {
+            25 => f16_to_f64(u16::from_be_bytes(
+                self.try_take_n(2)?
+                    .try_into()
+                    .map_err(|_| Error::InexistentSliceToArrayError)?,
+            )),
+            26 => f32::from_be_bytes(
+                self.try_take_n(4)?
+                    .try_into()
+                    .map_err(|_| Error::InexistentSliceToArrayError)?,
+            )
+            .into(),
+            27 => f64::from_be_bytes(
+                self.try_take_n(8)?
+                    .try_into()
+                    .map_err(|_| Error::InexistentSliceToArrayError)?,
+            ),
+            _ => return Err(Error::DeserializeBadFloat),
+        };
+        Ok(value)
     }
 
     // fn try_take_varint(&mut self) -> Result<usize> {
@@ -300,6 +821,8 @@ impl<'de> Deserializer<'de> {
 struct SeqAccess<'a, 'b: 'a> {
     deserializer: &'a mut Deserializer<'b>,
     len: usize,
+    // the last item was deserialized successfully
+    yielded: bool,
 }
 
 impl<'a, 'b: 'a> serde::de::SeqAccess<'b> for SeqAccess<'a, 'b> {
@@ -309,9 +832,12 @@ impl<'a, 'b: 'a> serde::de::SeqAccess<'b> for SeqAccess<'a, 'b> {
     where
         V: DeserializeSeed<'b>,
     {
+        self.yielded = false;
         if self.len > 0 {
             self.len -= 1;
-            Ok(Some(seed.deserialize(&mut *self.deserializer)?))
+            let item = seed.deserialize(&mut *self.deserializer)?;
+            self.yielded = true;
+            Ok(Some(item))
         } else {
             Ok(None)
         }
@@ -322,9 +848,55 @@ impl<'a, 'b: 'a> serde::de::SeqAccess<'b> for SeqAccess<'a, 'b> {
     }
 }
 
+/// The error for a visitor that reported an invalid length right after it received one of the
+/// `len` items or entries of an array, byte string or map, or for a byte string of length `len`
+/// as a whole.  Indefinite-length arrays and maps pass the number of items read so far.
+///
+/// This is how fixed-capacity containers such as `heapless::Vec` fail when an item does not fit.
+/// Errors of the items themselves are passed on unchanged.
+#[allow(unused_variables)] // only logged
+fn capacity_exceeded(len: usize) -> Error {
+    info_now!("deser capacity exceeded: {} items", len);
+    Error::DeserializeCapacityExceeded
+}
+
+/// Presents the contents of a byte string as a sequence of `u8` elements.
+struct ByteSeqAccess<'de> {
+    bytes: &'de [u8],
+    // the last element was deserialized successfully
+    yielded: bool,
+}
+
+impl<'de> serde::de::SeqAccess<'de> for ByteSeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.yielded = false;
+        match self.bytes.split_first() {
+            Some((byte, rest)) => {
+                self.bytes = rest;
+                let element =
+                    seed.deserialize(IntoDeserializer::<Error>::into_deserializer(*byte))?;
+                self.yielded = true;
+                Ok(Some(element))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.bytes.len())
+    }
+}
+
 struct MapAccess<'a, 'b: 'a> {
     deserializer: &'a mut Deserializer<'b>,
     len: usize,
+    // the last entry was deserialized successfully
+    yielded: bool,
 }
 
 impl<'a, 'b: 'a> serde::de::MapAccess<'b> for MapAccess<'a, 'b> {
@@ -334,6 +906,7 @@ impl<'a, 'b: 'a> serde::de::MapAccess<'b> for MapAccess<'a, 'b> {
     where
         V: DeserializeSeed<'b>,
     {
+        self.yielded = false;
         if self.len > 0 {
             self.len -= 1;
             Ok(Some(seed.deserialize(&mut *self.deserializer)?))
@@ -346,7 +919,9 @@ impl<'a, 'b: 'a> serde::de::MapAccess<'b> for MapAccess<'a, 'b> {
     where
         V: de::DeserializeSeed<'b>,
     {
-        seed.deserialize(&mut *self.deserializer)
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.yielded = true;
+        Ok(value)
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -354,6 +929,78 @@ impl<'a, 'b: 'a> serde::de::MapAccess<'b> for MapAccess<'a, 'b> {
     }
 }
 
+/// An array, map or tag that `Deserializer::ignore` is in the middle of.
+///
+/// This is kept small, as `ignore` reserves `MAX_DEPTH` of them on the stack.
+#[derive(Clone, Copy, Default)]
+struct Open {
+    // items left for definite lengths, items seen so far for indefinite lengths, counting map
+    // keys and values separately
+    items: u32,
+    indefinite: bool,
+    map: bool,
+}
+
+/// Access to the items of an indefinite-length array or map.
+struct IndefiniteAccess<'a, 'b: 'a> {
+    deserializer: &'a mut Deserializer<'b>,
+    // items or entries so far, for `Limits::max_collection_len`
+    len: usize,
+    // the break stop code has been consumed
+    done: bool,
+    // the last item or entry was deserialized successfully
+    yielded: bool,
+}
+
+impl<'a, 'b: 'a> IndefiniteAccess<'a, 'b> {
+    fn next<V: DeserializeSeed<'b>>(&mut self, seed: V) -> Result<Option<V::Value>> {
+        self.yielded = false;
+        if self.done || self.deserializer.consume_break()? {
+            self.done = true;
+            return Ok(None);
+        }
+        self.len += 1;
+        if self.len > self.deserializer.limits.max_collection_len {
+            return Err(Error::DeserializeLimitExceeded);
+        }
+        self.deserializer.count_items(1)?;
+        Ok(Some(seed.deserialize(&mut *self.deserializer)?))
+    }
+}
+
+impl<'a, 'b: 'a> serde::de::SeqAccess<'b> for IndefiniteAccess<'a, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'b>,
+    {
+        let item = self.next(seed)?;
+        self.yielded = item.is_some();
+        Ok(item)
+    }
+}
+
+impl<'a, 'b: 'a> serde::de::MapAccess<'b> for IndefiniteAccess<'a, 'b> {
+    type Error = Error;
+
+    fn next_key_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'b>,
+    {
+        self.next(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'b>,
+    {
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.yielded = true;
+        Ok(value)
+    }
+}
+
 struct EnumAccess<'a, 'b: 'a> {
     deserializer: &'a mut Deserializer<'b>,
     variant_len: usize,
@@ -381,10 +1028,7 @@ impl<'de, 'a> serde::de::VariantAccess<'de> for EnumAccess<'a, 'de> {
             return Err(Error::DeserializeBadEnum);
         }
 
-        visitor.visit_seq(SeqAccess {
-            deserializer: self.deserializer,
-            len,
-        })
+        self.deserializer.visit_seq(len, visitor)
     }
 
     fn struct_variant<V: Visitor<'de>>(
@@ -408,22 +1052,75 @@ impl<'de, 'a> serde::de::EnumAccess<'de> for EnumAccess<'a, 'de> {
         // if discriminant > 0xFFFF_FFFF {
         //     return Err(Error::DeserializeBadEnum);
         // }
-        let v = DeserializeSeed::deserialize(seed, discriminant.into_deserializer())?;
+        let v = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(discriminant))?;
         Ok((v, self))
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
-    // cbor-smol does not support structures not known at compile time
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    /// Deserialize an item based on its major type, for self-describing data.
+    ///
+    /// Negative integers below `i64::MIN` and bignums that fit are visited as `i128` or `u128`,
+    /// other bignums as their byte string.  Other tags are skipped, and null, undefined and other
+    /// simple values are visited as unit.  Indefinite-length strings cannot be borrowed from the
+    /// input and are rejected.
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        // We wont ever support this.
-        // If you need this, use `serde_cbor`.
-        Err(Error::WontImplement)
+        match self.peek_major()? {
+            MAJOR_POSINT => self.deserialize_u64(visitor),
+            MAJOR_NEGINT => {
+                let raw = self.raw_deserialize_u64(MAJOR_NEGINT)?;
+                match i64::try_from(raw) {
+                    Ok(value) => visitor.visit_i64(-1 - value),
+                    Err(_) => visitor.visit_i128(-1 - i128::from(raw)),
+                }
+            }
+            MAJOR_BYTES | MAJOR_STR if self.is_indefinite()? => {
+                Err(Error::DeserializeIndefiniteLength)
+            }
+            MAJOR_BYTES => self.deserialize_bytes(visitor),
+            MAJOR_STR => self.deserialize_str(visitor),
+            major @ (MAJOR_ARRAY | MAJOR_MAP) => self.nested(|de| {
+                if de.is_indefinite()? {
+                    de.consume()?;
+                    return de.visit_indefinite(major, visitor);
+                }
+                let len = de.collection_len(major)?;
+                if major == MAJOR_MAP {
+                    de.visit_map(len, visitor)
+                } else {
+                    de.visit_seq(len, visitor)
+                }
+            }),
+            MAJOR_TAG => {
+                let tag = self.raw_deserialize_u64(MAJOR_TAG)?;
+                if matches!(tag, TAG_POSITIVE_BIGNUM | TAG_NEGATIVE_BIGNUM)
+                    && self.peek_major()? == MAJOR_BYTES
+                    && !self.is_indefinite()?
+                {
+                    return self.visit_bignum(tag == TAG_NEGATIVE_BIGNUM, visitor);
+                }
+                self.nested(|de| de::Deserializer::deserialize_any(de, visitor))
+            }
+            _ => match self.peek()? {
+                VALUE_FALSE | VALUE_TRUE => self.deserialize_bool(visitor),
+                VALUE_NULL | VALUE_UNDEFINED => {
+                    self.consume()?;
+                    visitor.visit_unit()
+                }
+                0xf9..=0xfb => self.deserialize_f64(visitor),
+                // other simple values have no equivalent in the data model
+                0xe0..=0xf8 => match self.raw_deserialize_simple() {
+                    Ok(_) => visitor.visit_unit(),
+                    Err(_) => Err(Error::DeserializeBadEncoding),
+                },
+                _ => Err(Error::DeserializeBadEncoding),
+            },
+        }
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
@@ -565,57 +1262,68 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_u64(raw)
     }
 
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::NotYetImplemented)
+        let (negative, magnitude) = self.raw_deserialize_u128(Error::DeserializeBadI128)?;
+        let magnitude = i128::try_from(magnitude).map_err(|_| Error::DeserializeBadI128)?;
+        visitor.visit_i128(if negative { -1 - magnitude } else { magnitude })
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::NotYetImplemented)
+        match self.raw_deserialize_u128(Error::DeserializeBadU128)? {
+            (false, value) => visitor.visit_u128(value),
+            (true, _) => Err(Error::DeserializeBadU128),
+        }
     }
 
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        // not sure, can this be implemented?
-        // todo!("implement `deserialize_char`");
-        Err(Error::NotYetImplemented)
-        // let mut buf = [0u8; 4];
-        // let bytes = self.try_take_n(4)?;
-        // buf.copy_from_slice(bytes);
-        // let integer = u32::from_le_bytes(buf);
-        // visitor.visit_char(core::char::from_u32(integer).ok_or(Error::DeserializeBadChar)?)
+        let value = self.raw_deserialize_f64()?;
+        let single = value as f32;
+        // double precision is only accepted if no precision is lost
+        if f64::from(single) != value && !value.is_nan() {
+            return Err(Error::DeserializeBadFloat);
+        }
+        visitor.visit_f32(single)
     }
 
-    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let major = self.peek_major()?;
-        match major {
-            MAJOR_ARRAY => {
-                let len = self.raw_deserialize_u32(MAJOR_ARRAY)?;
-                visitor.visit_seq(SeqAccess {
-                    deserializer: self,
-                    len: len as usize,
-                })
-            }
-            MAJOR_BYTES => {
-                // major type 2: "byte string"
-                let length = self.raw_deserialize_u32(MAJOR_BYTES)? as usize;
-                let bytes: &'de [u8] = self.try_take_n(length)?;
-                visitor.visit_borrowed_bytes(bytes)
-            }
-            _ => Err(Error::DeserializeBadMajor),
+        let value = self.raw_deserialize_f64()?;
+        visitor.visit_f64(value)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // a text string holding a single Unicode scalar, as written by `serialize_char`
+        let length = self.string_len(MAJOR_STR)?;
+        let bytes: &'de [u8] = self.try_take_n(length)?;
+        let string_slice = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
+        let mut chars = string_slice.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(Error::DeserializeBadChar),
         }
     }
 
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_bytes(visitor, false)
+    }
+
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
@@ -628,7 +1336,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         // major type 3: "text string"
-        let length = self.raw_deserialize_u32(MAJOR_STR)? as usize;
+        let length = self.string_len(MAJOR_STR)?;
         let bytes: &'de [u8] = self.try_take_n(length)?;
         let string_slice = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
         visitor.visit_borrowed_str(string_slice)
@@ -669,17 +1377,42 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     // Unit struct means a named value containing no data.
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == UNDEFINED_NAME {
+            return match self.peek()? {
+                VALUE_UNDEFINED => {
+                    self.consume()?;
+                    visitor.visit_unit()
+                }
+                _ => Err(Error::DeserializeExpectedUndefined),
+            };
+        }
         self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == RAW_CBOR_NAME {
+            let input = self.input;
+            self.ignore()?;
+            let len = input.len() - self.input.len();
+            return visitor.visit_borrowed_bytes(&input[..len]);
+        }
+        if name == BYTE_ARRAY_NAME {
+            return self.visit_bytes(visitor, true);
+        }
+        if name == SIMPLE_NAME {
+            return match self.raw_deserialize_simple()? {
+                // false, true, null and undefined have their own types
+                SIMPLE_FALSE..=SIMPLE_UNDEFINED => Err(Error::DeserializeBadSimple),
+                value => visitor.visit_u8(value),
+            };
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -687,12 +1420,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let len = self.raw_deserialize_u32(MAJOR_ARRAY)? as usize;
-
-        visitor.visit_seq(SeqAccess {
-            deserializer: self,
-            len,
-        })
+        self.visit_array(visitor)
     }
 
     #[inline(never)]
@@ -700,11 +1428,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let len = self.raw_deserialize_u32(MAJOR_ARRAY)? as usize;
-        visitor.visit_seq(SeqAccess {
-            deserializer: self,
-            len,
-        })
+        self.visit_array(visitor)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -724,12 +1448,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let len = self.raw_deserialize_u32(MAJOR_MAP)? as usize;
-
-        visitor.visit_map(MapAccess {
-            deserializer: self,
-            len,
-        })
+        let len = self.collection_len(MAJOR_MAP)?;
+        self.visit_map(len, visitor)
     }
 
     fn deserialize_struct<V>(
@@ -785,10 +1505,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         match self.peek_major()? {
             // Data variant
             MAJOR_ARRAY => {
-                let len = self.raw_deserialize_u32(MAJOR_ARRAY)?;
+                let len = self.collection_len(MAJOR_ARRAY)?;
                 visitor.visit_enum(EnumAccess {
                     deserializer: self,
-                    variant_len: len as usize,
+                    variant_len: len,
                 })
             }
             // Unit variant
@@ -810,7 +1530,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 // Rust identifiers are always valid UTF-8 so we can assume that bytes are
                 // UTF-8-encoded strings.  This has the benefit that we only need a mapping from
                 // strings to fields (and the mapping from bytes to fields can be optimized out).
-                let length = self.raw_deserialize_u32(major)? as usize;
+                let length = self.string_len(major)?;
                 let bytes: &'de [u8] = self.try_take_n(length)?;
                 let string_slice =
                     core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
@@ -913,7 +1633,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 mod tests {
 
     // use super::*;
-    use super::from_bytes;
+    use super::{from_bytes, from_bytes_lenient, MAX_DEPTH};
+    use crate::Error;
 
     // use crate::serde::{cbor_serialize, cbor_serialize2, cbor_deserialize};
     // use crate::serde::{cbor_serialize, cbor_serialize_old, cbor_deserialize};
@@ -971,7 +1692,7 @@ mod tests {
     fn de_i16() {
         let mut buf = [0u8; 64];
 
-        for number in i16::min_value()..=i16::MAX {
+        for number in i16::MIN..=i16::MAX {
             println!("testing {}", number);
             let _n = cbor_serialize(&number, &mut buf).unwrap();
             let de: i16 = from_bytes(&buf).unwrap();
@@ -1023,17 +1744,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn de_i128() {
+        let mut buf = [0; 32];
+        let cases: &[(i128, &[u8])] = &[
+            (0, &[0x00]),
+            (-1, &[0x20]),
+            (
+                u64::MAX.into(),
+                &[0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (
+                i128::from(u64::MAX) + 1,
+                &[
+                    0xc2, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                ],
+            ),
+            (
+                -1 - i128::from(u64::MAX),
+                &[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (
+                -2 - i128::from(u64::MAX),
+                &[
+                    0xc3, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                ],
+            ),
+            (
+                i128::MIN,
+                &[
+                    0xc3, 0x50, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                    0xff, 0xff, 0xff, 0xff, 0xff,
+                ],
+            ),
+        ];
+        for (value, encoded) in cases {
+            assert_eq!(cbor_serialize(value, &mut buf).unwrap(), *encoded);
+            assert_eq!(from_bytes::<i128>(encoded), Ok(*value));
+        }
+
+        let max = cbor_serialize(&u128::MAX, &mut buf).unwrap();
+        assert_eq!(max[..2], [0xc2, 0x50]);
+        assert_eq!(from_bytes::<u128>(max), Ok(u128::MAX));
+        assert_eq!(from_bytes::<i128>(max), Err(Error::DeserializeBadI128));
+        assert_eq!(from_bytes::<u128>(&[0x20]), Err(Error::DeserializeBadU128));
+        // fits into a plain integer, leading zero
+        assert_eq!(
+            from_bytes::<u128>(&[0xc2, 0x41, 0x01]),
+            Err(Error::DeserializeNonMinimal)
+        );
+        assert_eq!(
+            from_bytes::<u128>(&[0xc2, 0x4a, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(Error::DeserializeNonMinimal)
+        );
+        assert_eq!(
+            from_bytes::<u128>(&[0xc4, 0x41, 0x01]),
+            Err(Error::DeserializeBadTag)
+        );
+        assert_eq!(
+            from_bytes::<u128>(&[0xc2, 0x51, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(Error::DeserializeBadU128)
+        );
+    }
+
     #[test]
     fn de_i32() {
         let mut buf = [0u8; 64];
 
         let number: i32 = -98304;
         let ser = cbor_serialize(&number, &mut buf).unwrap();
-        println!("serialized number: {:?} of {}", ser, i16::min_value());
+        println!("serialized number: {:?} of {}", ser, i16::MIN);
         let de: i32 = from_bytes(ser).unwrap();
         assert_eq!(de, number);
 
-        for number in (3 * i16::min_value() as i32)..=3 * (i16::MAX as i32) {
+        for number in (3 * i16::MIN as i32)..=3 * (i16::MAX as i32) {
             println!("testing {}", number);
             let ser = cbor_serialize(&number, &mut buf).unwrap();
             let de: i32 = from_bytes(ser).unwrap();
@@ -1047,7 +1831,7 @@ mod tests {
             assert_eq!(de, number);
         }
 
-        for number in i32::min_value()..=(i32::min_value() - i16::min_value() as i32) {
+        for number in i32::MIN..=(i32::MIN - i16::MIN as i32) {
             println!("testing {}", number);
             let ser = cbor_serialize(&number, &mut buf).unwrap();
             let de: i32 = from_bytes(ser).unwrap();
@@ -1079,6 +1863,142 @@ mod tests {
         }
     }
 
+    #[test]
+    fn de_byte_seq() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Key {
+            id: [u8; 4],
+            data: heapless_v0_8::Vec<u8, 8>,
+        }
+
+        // both as byte strings and as arrays of integers
+        let bytes = [
+            0xa2, 0x62, b'i', b'd', 0x44, 1, 2, 3, 4, 0x64, b'd', b'a', b't', b'a', 0x42, 5, 6,
+        ];
+        let array = [
+            0xa2, 0x62, b'i', b'd', 0x84, 1, 2, 3, 4, 0x64, b'd', b'a', b't', b'a', 0x82, 5, 6,
+        ];
+        let key = Key {
+            id: [1, 2, 3, 4],
+            data: heapless_v0_8::Vec::from_slice(&[5, 6]).unwrap(),
+        };
+        assert_eq!(from_bytes::<Key>(&bytes), Ok(key));
+        assert_eq!(from_bytes(&bytes), from_bytes::<Key>(&array));
+
+        assert_eq!(from_bytes::<(u8, u8)>(&[0x42, 0xff, 0x00]), Ok((0xff, 0)));
+        assert_eq!(from_bytes::<[u8; 2]>(&[0x43, 1, 2, 3]), Ok([1, 2]));
+        let mut de = super::Deserializer::from_bytes(&[0x43, 1, 2, 3]).reject_trailing_items(true);
+        assert_eq!(
+            <[u8; 2] as serde::Deserialize>::deserialize(&mut de),
+            Err(Error::DeserializeTrailingItems)
+        );
+        assert_eq!(
+            from_bytes::<[u8; 2]>(&[0x41, 1]),
+            Err(Error::DeserializeBadLength)
+        );
+        assert_eq!(
+            from_bytes::<[u8; 2]>(&[0x42, 1]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        // the elements must be able to hold a byte
+        assert!(from_bytes::<[bool; 1]>(&[0x41, 1]).is_err());
+        assert_eq!(
+            from_bytes::<heapless_v0_8::Vec<u16, 2>>(&[0x42, 1, 2]).unwrap(),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn de_capacity() {
+        use heapless_v0_8::{LinearMap, Vec};
+
+        // fieldless, so `Error` stays a single byte
+        assert_eq!(core::mem::size_of::<Error>(), 1);
+        let exceeded = Error::DeserializeCapacityExceeded;
+        assert_eq!(from_bytes::<Vec<u8, 2>>(&[0x83, 1, 2, 3]), Err(exceeded));
+        assert_eq!(from_bytes::<Vec<u8, 2>>(&[0x43, 1, 2, 3]), Err(exceeded));
+        assert_eq!(
+            from_bytes::<(u8, Vec<u8, 2>)>(&[0x82, 0, 0x83, 1, 2, 3]),
+            Err(exceeded)
+        );
+        assert_eq!(
+            from_bytes::<LinearMap<u8, u8, 1>>(&[0xa2, 1, 1, 2, 2]),
+            Err(exceeded)
+        );
+        assert_eq!(
+            from_bytes::<Vec<Vec<u8, 2>, 1>>(&[0x81, 0x83, 1, 2, 3]),
+            Err(exceeded)
+        );
+        assert_eq!(
+            from_bytes::<Vec<u8, 3>>(&[0x83, 1, 2, 3]).unwrap(),
+            [1, 2, 3]
+        );
+        // too few items are not a capacity problem, neither in the container nor in its items
+        assert_eq!(
+            from_bytes::<[u8; 2]>(&[0x81, 1]),
+            Err(Error::DeserializeBadLength)
+        );
+        assert_eq!(
+            from_bytes::<Vec<[u8; 2], 4>>(&[0x81, 0x81, 0x01]),
+            Err(Error::DeserializeBadLength)
+        );
+        assert_eq!(
+            from_bytes::<LinearMap<u8, [u8; 2], 4>>(&[0xa1, 0x01, 0x81, 0x01]),
+            Err(Error::DeserializeBadLength)
+        );
+        // byte strings are visited as a whole
+        assert_eq!(
+            from_bytes::<heapless_bytes_v0_4::Bytes<2>>(&[0x43, 1, 2, 3]),
+            Err(exceeded)
+        );
+        assert_eq!(
+            from_bytes::<heapless_bytes_v0_4::Bytes<2>>(&[0x83, 1, 2, 3]),
+            Err(exceeded)
+        );
+        assert_eq!(
+            from_bytes::<heapless_bytes_v0_4::Bytes<3>>(&[0x43, 1, 2, 3]).unwrap(),
+            [1, 2, 3]
+        );
+        // indefinite lengths, which only `deserialize_any` accepts
+        struct Any<T>(Vec<T, 2>);
+        impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Any<T> {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct AnyVisitor<T>(core::marker::PhantomData<T>);
+                impl<'de, T: serde::Deserialize<'de>> serde::de::Visitor<'de> for AnyVisitor<T> {
+                    type Value = Any<T>;
+                    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                        f.write_str("at most two items")
+                    }
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Any<T>, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        let mut items = Vec::new();
+                        while let Some(item) = seq.next_element()? {
+                            items
+                                .push(item)
+                                .map_err(|_| serde::de::Error::invalid_length(3, &self))?;
+                        }
+                        Ok(Any(items))
+                    }
+                }
+                deserializer.deserialize_any(AnyVisitor(core::marker::PhantomData))
+            }
+        }
+        assert_eq!(
+            from_bytes::<Any<u8>>(&[0x9f, 1, 2, 0xff]).unwrap().0,
+            [1, 2]
+        );
+        assert!(matches!(
+            from_bytes::<Any<u8>>(&[0x9f, 1, 2, 3, 0xff]),
+            Err(Error::DeserializeCapacityExceeded)
+        ));
+        assert!(matches!(
+            from_bytes::<Any<[u8; 2]>>(&[0x9f, 0x81, 0x01, 0xff]),
+            Err(Error::DeserializeBadLength)
+        ));
+    }
+
     #[test]
     fn de_str() {
         let mut buf = [0u8; 64];
@@ -1091,6 +2011,95 @@ mod tests {
         assert_eq!(de, string_slice);
     }
 
+    #[test]
+    fn de_lenient() {
+        // 5 in one, two, four and eight bytes
+        for input in [
+            &[0x18, 0x05][..],
+            &[0x19, 0x00, 0x05],
+            &[0x1a, 0x00, 0x00, 0x00, 0x05],
+            &[0x1b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05],
+        ] {
+            assert_eq!(from_bytes::<u64>(input), Err(Error::DeserializeNonMinimal));
+            assert_eq!(from_bytes_lenient::<u8>(input), Ok(5));
+            assert_eq!(from_bytes_lenient::<u16>(input), Ok(5));
+            assert_eq!(from_bytes_lenient::<u32>(input), Ok(5));
+            assert_eq!(from_bytes_lenient::<u64>(input), Ok(5));
+            assert_eq!(from_bytes_lenient::<i8>(input), Ok(5));
+        }
+        assert_eq!(from_bytes_lenient::<i32>(&[0x39, 0x00, 0x05]), Ok(-6));
+        assert_eq!(
+            from_bytes_lenient::<u8>(&[0x19, 0x01, 0x00]),
+            Err(Error::DeserializeBadU8)
+        );
+        assert_eq!(
+            from_bytes_lenient::<u32>(&[0x1b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]),
+            Err(Error::DeserializeBadU32)
+        );
+
+        // lengths
+        let input = [0x99, 0x00, 0x02, 0x78, 0x01, b'a', 0x19, 0x00, 0x07];
+        assert_eq!(
+            from_bytes::<(&str, u8)>(&input),
+            Err(Error::DeserializeNonMinimal)
+        );
+        assert_eq!(from_bytes_lenient::<(&str, u8)>(&input), Ok(("a", 7)));
+        assert_eq!(
+            super::item_len(&input[..6]),
+            Err(super::ItemLenError::Invalid(Error::DeserializeNonMinimal))
+        );
+
+        // skipped integers and tags
+        use serde::de::IgnoredAny;
+        for input in [&[0x18, 0x05][..], &[0x39, 0x00, 0x05], &[0xd8, 0x01, 0x00]] {
+            assert_eq!(
+                from_bytes::<IgnoredAny>(input),
+                Err(Error::DeserializeNonMinimal)
+            );
+            assert_eq!(from_bytes_lenient::<IgnoredAny>(input), Ok(IgnoredAny));
+        }
+
+        // bignums that fit into a plain integer, with leading zeros
+        assert_eq!(from_bytes_lenient::<u128>(&[0xc2, 0x41, 0x01]), Ok(1));
+        assert_eq!(
+            from_bytes_lenient::<i128>(&[0xc3, 0x42, 0x00, 0x01]),
+            Ok(-2)
+        );
+        let mut input = [0u8; 20];
+        input[..2].copy_from_slice(&[0xc2, 0x52]);
+        input[4] = 0x01;
+        assert_eq!(from_bytes_lenient::<u128>(&input), Ok(1 << 120));
+        input[3] = 0x01;
+        assert_eq!(
+            from_bytes_lenient::<u128>(&input),
+            Err(Error::DeserializeBadU128)
+        );
+
+        // two-byte simple values below 32 are malformed, not just long
+        assert_eq!(
+            from_bytes_lenient::<crate::Simple>(&[0xf8, 0x10]),
+            Err(Error::DeserializeBadSimple)
+        );
+    }
+
+    #[test]
+    fn de_char() {
+        let mut buf = [0u8; 8];
+        for c in ['a', 'ä', '€', '🐝'] {
+            let ser = cbor_serialize(&c, &mut buf).unwrap();
+            assert_eq!(from_bytes::<char>(ser), Ok(c));
+        }
+        assert_eq!(from_bytes::<char>(&[0x60]), Err(Error::DeserializeBadChar));
+        assert_eq!(
+            from_bytes::<char>(&[0x62, b'a', b'b']),
+            Err(Error::DeserializeBadChar)
+        );
+        assert_eq!(
+            from_bytes::<char>(&[0x41, b'a']),
+            Err(Error::DeserializeBadMajor)
+        );
+    }
+
     // #[test]
     // fn de_struct() {
     //     use crate::ctap2::get_info::CtapOptions;
@@ -1290,6 +2299,369 @@ mod tests {
         assert_eq!(de.val, "value");
     }
 
+    #[test]
+    fn de_float() {
+        let mut buf = [0; 16];
+        for value in [0.0, 1.5, -4.1, 65504.0, 1.0e300, f64::INFINITY] {
+            let ser = cbor_serialize(&value, &mut buf).unwrap();
+            assert_eq!(from_bytes::<f64>(ser), Ok(value));
+        }
+        for value in [0.0f32, 1.5, -4.1, f32::NEG_INFINITY] {
+            let ser = cbor_serialize(&value, &mut buf).unwrap();
+            assert_eq!(from_bytes::<f32>(ser), Ok(value));
+        }
+        assert!(from_bytes::<f64>(&[0xf9, 0x7e, 0x00]).unwrap().is_nan());
+        assert_eq!(
+            from_bytes::<f32>(&[0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]),
+            Err(Error::DeserializeBadFloat)
+        );
+        assert_eq!(from_bytes::<f64>(&[0x01]), Err(Error::DeserializeBadMajor));
+    }
+
+    #[test]
+    fn de_any() {
+        use core::fmt;
+        use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+        /// Counts the scalar items in any data.
+        #[derive(Debug, PartialEq)]
+        struct Count(usize);
+
+        impl<'de> Deserialize<'de> for Count {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(CountVisitor)
+            }
+        }
+
+        struct CountVisitor;
+
+        impl<'de> Visitor<'de> for CountVisitor {
+            type Value = Count;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any value")
+            }
+
+            fn visit_bool<E>(self, _: bool) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_i64<E>(self, _: i64) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_i128<E>(self, _: i128) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_u64<E>(self, _: u64) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_f64<E>(self, _: f64) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_str<E>(self, _: &str) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_bytes<E>(self, _: &[u8]) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_unit<E>(self) -> Result<Count, E> {
+                Ok(Count(1))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Count, A::Error> {
+                let mut count = 0;
+                while let Some(Count(n)) = seq.next_element()? {
+                    count += n;
+                }
+                Ok(Count(count))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Count, A::Error> {
+                let mut count = 0;
+                while let Some((Count(k), Count(v))) = map.next_entry()? {
+                    count += k + v;
+                }
+                Ok(Count(count))
+            }
+        }
+
+        // {1: h'ff', "a": [_ -2, 1.5, 1(0), undefined, true], "b": {_ }}
+        let input = [
+            0xa3, 0x01, 0x41, 0xff, 0x61, b'a', 0x9f, 0x21, 0xf9, 0x3e, 0x00, 0xc1, 0x00, 0xf7,
+            0xf5, 0xff, 0x61, b'b', 0xbf, 0xff,
+        ];
+        assert_eq!(from_bytes::<Count>(&input), Ok(Count(9)));
+        assert_eq!(
+            from_bytes::<Count>(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Ok(Count(1))
+        );
+
+        assert_eq!(
+            from_bytes::<Count>(&[0x5f, 0xff]),
+            Err(Error::DeserializeIndefiniteLength)
+        );
+        assert_eq!(
+            from_bytes::<Count>(&[0xff]),
+            Err(Error::DeserializeBadEncoding)
+        );
+        assert_eq!(
+            from_bytes::<Count>(&[0x9f, 0x01]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        let deep = [0x81; MAX_DEPTH + 1];
+        assert_eq!(
+            from_bytes::<Count>(&deep),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+        // the depth is restored after an error
+        let mut de = super::Deserializer::from_bytes(&[0x81, 0x61, 0xff]);
+        assert_eq!(Count::deserialize(&mut de), Err(Error::DeserializeBadUtf8));
+        assert_eq!(de.depth, MAX_DEPTH);
+    }
+
+    #[test]
+    fn de_limits() {
+        use super::{from_bytes_with_limits, Deserializer, Limits};
+        use serde::de::IgnoredAny;
+
+        let limits = Limits {
+            max_collection_len: 2,
+            max_string_len: 3,
+            max_items: 3,
+            max_input_len: 8,
+        };
+        let limited = |input| from_bytes_with_limits::<IgnoredAny>(input, limits).map(|_| ());
+        assert_eq!(limited(&[0x82, 0x01, 0x63, b'a', b'b', b'c']), Ok(()));
+        for input in [
+            // too many items, definite and indefinite
+            &[0x83, 0x01, 0x02, 0x03][..],
+            &[0x9f, 0x01, 0x02, 0x03, 0xff],
+            &[0xa3, 0x01, 0x01, 0x02, 0x02, 0x03, 0x03],
+            // strings too long, definite and indefinite
+            &[0x44, 0x01, 0x02, 0x03, 0x04],
+            &[0x5f, 0x42, 0x01, 0x02, 0x42, 0x03, 0x04, 0xff],
+            // too many items in total
+            &[0x82, 0x82, 0x01, 0x02, 0x81, 0x03],
+            // input too long
+            &[0x49, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        ] {
+            assert_eq!(limited(input), Err(Error::DeserializeLimitExceeded));
+        }
+        assert_eq!(
+            from_bytes_with_limits::<(u8, u8, u8)>(&[0x83, 0x01, 0x02, 0x03], limits),
+            Err(Error::DeserializeLimitExceeded)
+        );
+        assert_eq!(
+            from_bytes_with_limits::<&str>(&[0x64, b'a', b'b', b'c', b'd'], limits),
+            Err(Error::DeserializeLimitExceeded)
+        );
+        assert!(Deserializer::with_limits(&[0; 9], limits).is_err());
+
+        // a header that claims more items than the input can hold fails immediately
+        let hostile = [0x9a, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(
+            from_bytes::<IgnoredAny>(&hostile).map(|_| ()),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            super::item_len(&hostile),
+            Err(super::ItemLenError::Incomplete)
+        );
+    }
+
+    #[test]
+    fn de_in_place() {
+        use crate::deserialize_in_place;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+        struct State {
+            counter: u32,
+            keys: [[u8; 4]; 3],
+            label: Option<(u8, u8)>,
+        }
+
+        let mut buf = [0u8; 128];
+        let state = State {
+            counter: 100_000,
+            keys: [[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]],
+            label: Some((3, 4)),
+        };
+        let ser = cbor_serialize(&state, &mut buf).unwrap();
+
+        let mut place = State {
+            counter: 0,
+            keys: [[0; 4]; 3],
+            label: None,
+        };
+        deserialize_in_place(ser, &mut place).unwrap();
+        assert_eq!(place, state);
+
+        let mut place = [0u8; 3];
+        deserialize_in_place(&[0x83, 0x01, 0x02, 0x03], &mut place).unwrap();
+        assert_eq!(place, [1, 2, 3]);
+        // trailing items are skipped, or rejected if configured
+        let input = [0x82, 0x84, 0x05, 0x06, 0x07, 0x08, 0x09];
+        assert_eq!(
+            cbor_deserialize::<([u8; 3], u8)>(&input),
+            Ok(([5, 6, 7], 9))
+        );
+        let mut de = super::Deserializer::from_bytes(&input).reject_trailing_items(true);
+        assert_eq!(
+            <([u8; 3], u8)>::deserialize_in_place(&mut de, &mut ([0; 3], 0)),
+            Err(Error::DeserializeTrailingItems)
+        );
+        let input = [0xa2, 0x01, 0x02, 0x03, 0x04];
+        let mut de = super::Deserializer::from_bytes(&input).reject_trailing_items(true);
+        assert_eq!(
+            heapless_v0_8::LinearMap::<u8, u8, 2>::deserialize(&mut de).map(|map| map.len()),
+            Ok(2)
+        );
+        let mut de =
+            super::Deserializer::from_bytes(&[0x83, 0x01, 0x02, 0x03]).reject_trailing_items(true);
+        assert_eq!(
+            <(u8, u8)>::deserialize(&mut de),
+            Err(Error::DeserializeTrailingItems)
+        );
+    }
+
+    #[test]
+    fn de_ignore() {
+        use super::{take_from_bytes, MAX_DEPTH};
+        use serde::de::IgnoredAny;
+
+        // [_ {_ 1: [_ ]}, 6(2), [_ 6([_ 3])]], 4
+        let input = [
+            0x9f, 0xbf, 0x01, 0x9f, 0xff, 0xff, 0xc6, 0x02, 0x9f, 0xc6, 0x9f, 0x03, 0xff, 0xff,
+            0xff, 0x04,
+        ];
+        let (_, rest) = take_from_bytes::<IgnoredAny>(&input).unwrap();
+        assert_eq!(rest, [0x04]);
+        for len in 0..input.len() - 1 {
+            assert_eq!(
+                from_bytes::<IgnoredAny>(&input[..len]),
+                Err(Error::DeserializeUnexpectedEnd)
+            );
+        }
+
+        // MAX_DEPTH levels of indefinite arrays, indefinite maps and tags
+        let mut deep = Vec::new();
+        for level in 0..MAX_DEPTH {
+            deep.extend_from_slice(match level % 3 {
+                0 => &[0x9f][..],
+                1 => &[0xbf, 0x01],
+                _ => &[0xc6],
+            });
+        }
+        deep.push(0x00);
+        for level in (0..MAX_DEPTH).rev() {
+            if level % 3 != 2 {
+                deep.push(0xff);
+            }
+        }
+        let (_, rest) = take_from_bytes::<IgnoredAny>(&deep).unwrap();
+        assert!(rest.is_empty());
+        deep.insert(0, 0x9f);
+        deep.push(0xff);
+        assert_eq!(
+            from_bytes::<IgnoredAny>(&deep),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+        // the stack usage does not depend on the input
+        assert_eq!(
+            from_bytes::<IgnoredAny>(&[0x9f; 100_000]),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+
+        // an indefinite-length map with a key but no value
+        assert_eq!(
+            from_bytes::<IgnoredAny>(&[0xbf, 0x01, 0x02, 0x03, 0xff]),
+            Err(Error::DeserializeBadEncoding)
+        );
+    }
+
+    #[test]
+    fn de_item_len() {
+        use super::{item_len, ItemLenError, MAX_DEPTH};
+
+        let input = [
+            0x82, 0x01, 0xa1, 0x61, b'a', 0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0,
+        ];
+        assert_eq!(item_len(&input), Ok(input.len()));
+        for len in 0..input.len() {
+            assert_eq!(item_len(&input[..len]), Err(ItemLenError::Incomplete));
+        }
+        assert_eq!(item_len(&[0x01, 0x02]), Ok(1));
+
+        // indefinite-length array holding an indefinite-length byte string
+        let input = [0x9f, 0x5f, 0x41, 0x01, 0x40, 0xff, 0x02, 0xff];
+        assert_eq!(item_len(&input), Ok(input.len()));
+        assert_eq!(item_len(&input[..7]), Err(ItemLenError::Incomplete));
+
+        assert_eq!(
+            item_len(&[0x1c]),
+            Err(ItemLenError::Invalid(Error::DeserializeBadU16))
+        );
+        assert_eq!(
+            item_len(&[0x82, 0x01, 0xff]),
+            Err(ItemLenError::Invalid(Error::DeserializeBadEncoding))
+        );
+        // reserved simple value
+        assert_eq!(
+            item_len(&[0xf8, 0x18]),
+            Err(ItemLenError::Invalid(Error::DeserializeBadEncoding))
+        );
+        assert_eq!(item_len(&[0xf8, 0x20]), Ok(2));
+
+        let nested = [0x81; MAX_DEPTH + 1];
+        assert_eq!(
+            item_len(&nested),
+            Err(ItemLenError::Invalid(Error::DeserializeDepthLimitExceeded))
+        );
+        assert_eq!(item_len(&nested[1..]), Err(ItemLenError::Incomplete));
+
+        // MAX_DEPTH levels of arrays, indefinite maps and tags around an empty array
+        let mut deep = [0u8; 3 * MAX_DEPTH];
+        let mut len = 0;
+        for level in 0..MAX_DEPTH - 1 {
+            let header: &[u8] = match level % 3 {
+                0 => &[0x82, 0x01],
+                1 => &[0xbf, 0x00],
+                _ => &[0xc6],
+            };
+            deep[len..len + header.len()].copy_from_slice(header);
+            len += header.len();
+        }
+        deep[len] = 0x80;
+        len += 1;
+        for level in (0..MAX_DEPTH - 1).rev() {
+            if level % 3 == 1 {
+                deep[len] = 0xff;
+                len += 1;
+            }
+        }
+        assert_eq!(item_len(&deep[..len]), Ok(len));
+        assert_eq!(item_len(&deep[..len - 1]), Err(ItemLenError::Incomplete));
+        let tagged = [0xc6; MAX_DEPTH + 1];
+        assert_eq!(item_len(&tagged[1..]), Err(ItemLenError::Incomplete));
+        assert_eq!(
+            item_len(&tagged),
+            Err(ItemLenError::Invalid(Error::DeserializeDepthLimitExceeded))
+        );
+
+        // break in place of a map value
+        assert_eq!(
+            item_len(&[0xbf, 0x01, 0xff]),
+            Err(ItemLenError::Invalid(Error::DeserializeBadEncoding))
+        );
+    }
+
     // #[test]
     // fn fuzzer_things() {
     //     let data: [u8; 2] = [160, 96];
@@ -0,0 +1,352 @@
+//! Diagnostic notation ([RFC 8949 §8](https://www.rfc-editor.org/rfc/rfc8949#section-8)).
+//!
+//! [`Diag`][] renders arbitrary CBOR data in a human-readable form, for example
+//! `{1: h'0102', "rk": true}`, without knowing its type.  It writes through a
+//! [`core::fmt::Write`][] and does not allocate, so it can be used in logs:
+//!
+//! ```
+//! use cbor_smol::diag::Diag;
+//!
+//! let input = [0xa2, 0x01, 0x42, 0x01, 0x02, 0x62, b'r', b'k', 0xf5];
+//! assert_eq!(Diag::new(&input).to_string(), r#"{1: h'0102', "rk": true}"#);
+//! assert_eq!(
+//!     format!("{:#}", Diag::new(&input)),
+//!     "{\n  1: h'0102',\n  \"rk\": true\n}"
+//! );
+//! ```
+//!
+//! With the `diag-parse` feature, `parse` converts diagnostic notation back to CBOR, which
+//! is handy to write readable test vectors.
+
+use core::fmt::{self, Write};
+
+use crate::de::MAX_DEPTH;
+use crate::error::{Error, Result};
+use crate::token::{Decoder, Token};
+
+#[cfg(feature = "diag-parse")]
+mod parse;
+#[cfg(feature = "diag-parse")]
+pub use parse::{parse, parse_to};
+
+/// Renders CBOR data in diagnostic notation.
+///
+/// If the input contains more than one item, it is rendered as a CBOR Sequence, with the items
+/// separated by commas.
+#[derive(Clone, Copy, Debug)]
+pub struct Diag<'a> {
+    input: &'a [u8],
+    pretty: bool,
+}
+
+impl<'a> Diag<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pretty: false,
+        }
+    }
+
+    /// Put each item of an array or map on its own, indented line.
+    ///
+    /// The alternate flag of the formatter (`{:#}`) has the same effect.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Write the diagnostic notation of the input to `out`.
+    ///
+    /// Stops at the first malformed item.  If `out` fails, [`Error::SerializeBufferFull`][] is
+    /// returned.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mut printer = Printer {
+            decoder: Decoder::new(self.input),
+            out,
+            pretty: self.pretty,
+            depth: 0,
+        };
+        let mut first = true;
+        while !printer.decoder.remaining().is_empty() {
+            if !first {
+                printer.write(",")?;
+                printer.space(0)?;
+            }
+            printer.item(0)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// If the input is malformed, the output ends with the error in angle brackets.
+impl fmt::Display for Diag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diag = self.pretty(self.pretty || f.alternate());
+        match diag.write_to(f) {
+            Ok(()) => Ok(()),
+            Err(Error::SerializeBufferFull) => Err(fmt::Error),
+            Err(err) => write!(f, "<{}>", err),
+        }
+    }
+}
+
+struct Printer<'a, 'w, W> {
+    decoder: Decoder<'a>,
+    out: &'w mut W,
+    pretty: bool,
+    depth: usize,
+}
+
+impl<W: Write> Printer<'_, '_, W> {
+    fn write(&mut self, s: &str) -> Result<()> {
+        self.out
+            .write_str(s)
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        self.out
+            .write_fmt(args)
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// Separate two items, on a new line with the given indentation in pretty mode.
+    fn space(&mut self, level: usize) -> Result<()> {
+        if self.pretty {
+            self.write("\n")?;
+            for _ in 0..level {
+                self.write("  ")?;
+            }
+            Ok(())
+        } else {
+            self.write(" ")
+        }
+    }
+
+    fn item(&mut self, level: usize) -> Result<()> {
+        if self.depth > MAX_DEPTH {
+            return Err(Error::DeserializeDepthLimitExceeded);
+        }
+        match self.decoder.next_token()? {
+            Token::UInt(value) => write!(self, "{}", value),
+            Token::NegInt(value) => write!(self, "-{}", u128::from(value) + 1),
+            Token::Bytes(bytes) => self.bytes(bytes),
+            Token::Text(text) => self.text(text),
+            token @ (Token::BytesIndefinite | Token::TextIndefinite) => {
+                self.chunks(token == Token::TextIndefinite)
+            }
+            Token::Array(len) => self.container("[", "]", Some(len), false, level),
+            Token::ArrayIndefinite => self.container("[_", "]", None, false, level),
+            Token::Map(len) => self.container("{", "}", Some(len), true, level),
+            Token::MapIndefinite => self.container("{_", "}", None, true, level),
+            Token::Tag(tag) => {
+                write!(self, "{}(", tag)?;
+                self.depth += 1;
+                self.item(level)?;
+                self.depth -= 1;
+                self.write(")")
+            }
+            Token::Bool(true) => self.write("true"),
+            Token::Bool(false) => self.write("false"),
+            Token::Null => self.write("null"),
+            Token::Undefined => self.write("undefined"),
+            Token::Simple(value) => write!(self, "simple({})", value),
+            Token::Float(value) => self.float(value),
+            Token::Break => Err(Error::DeserializeBadEncoding),
+        }
+    }
+
+    fn container(
+        &mut self,
+        open: &str,
+        close: &str,
+        len: Option<u64>,
+        map: bool,
+        level: usize,
+    ) -> Result<()> {
+        self.write(open)?;
+        self.depth += 1;
+        let mut count = 0;
+        loop {
+            match len {
+                Some(len) if count == len => break,
+                None if self.decoder.at_break() => {
+                    self.decoder.next_token()?;
+                    break;
+                }
+                _ => {}
+            }
+            if count > 0 {
+                self.write(",")?;
+            }
+            if count > 0 || len.is_none() || self.pretty {
+                self.space(level + 1)?;
+            }
+            self.item(level + 1)?;
+            if map {
+                self.write(": ")?;
+                self.item(level + 1)?;
+            }
+            count += 1;
+        }
+        self.depth -= 1;
+        if count > 0 && self.pretty {
+            self.space(level)?;
+        } else if count == 0 && len.is_none() {
+            self.write(" ")?;
+        }
+        self.write(close)
+    }
+
+    /// The chunks of an indefinite-length string, `(_ h'01', h'02')`.
+    fn chunks(&mut self, text: bool) -> Result<()> {
+        let mut first = true;
+        loop {
+            let token = self.decoder.next_token()?;
+            match token {
+                Token::Break if first => return self.write(if text { "\"\"_" } else { "''_" }),
+                Token::Break => return self.write(")"),
+                Token::Bytes(_) | Token::Text(_) => {}
+                _ => return Err(Error::DeserializeBadEncoding),
+            }
+            self.write(if first { "(_ " } else { ", " })?;
+            match token {
+                Token::Bytes(bytes) if !text => self.bytes(bytes)?,
+                Token::Text(chunk) if text => self.text(chunk)?,
+                _ => return Err(Error::DeserializeBadEncoding),
+            }
+            first = false;
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write("h'")?;
+        for byte in bytes {
+            write!(self, "{:02x}", byte)?;
+        }
+        self.write("'")
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        self.write("\"")?;
+        for c in text.chars() {
+            match c {
+                '"' => self.write("\\\"")?,
+                '\\' => self.write("\\\\")?,
+                '\n' => self.write("\\n")?,
+                '\r' => self.write("\\r")?,
+                '\t' => self.write("\\t")?,
+                c if c.is_control() => write!(self, "\\u{:04x}", u32::from(c))?,
+                c => write!(self, "{}", c)?,
+            }
+        }
+        self.write("\"")
+    }
+
+    fn float(&mut self, value: f64) -> Result<()> {
+        if value.is_nan() {
+            self.write("NaN")
+        } else if value.is_infinite() {
+            self.write(if value > 0.0 { "Infinity" } else { "-Infinity" })
+        } else {
+            // the debug representation always has a decimal point or an exponent
+            write!(self, "{:?}", value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diag;
+    use crate::Error;
+
+    #[test]
+    fn diag() {
+        for (input, expected) in [
+            (&[0x00][..], "0"),
+            (
+                &[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                "-18446744073709551616",
+            ),
+            (&[0x43, 0x01, 0xab, 0xff], "h'01abff'"),
+            (
+                &[0x65, b'a', b'"', b'\\', b'\n', 0x01],
+                r#""a\"\\\n\u0001""#,
+            ),
+            (&[0x62, 0xc3, 0xbc], "\"ü\""),
+            (
+                &[0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff],
+                "(_ h'01', h'0203')",
+            ),
+            (&[0x5f, 0xff], "''_"),
+            (&[0x7f, 0x61, b'a', 0xff], "(_ \"a\")"),
+            (&[0x80], "[]"),
+            (&[0xa0], "{}"),
+            (&[0x9f, 0xff], "[_ ]"),
+            (&[0x83, 0x01, 0x81, 0x02, 0xa0], "[1, [2], {}]"),
+            (
+                &[0xbf, 0x61, b'a', 0x9f, 0x01, 0xff, 0xff],
+                "{_ \"a\": [_ 1]}",
+            ),
+            (&[0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0], "1(1363896240)"),
+            (&[0xd8, 0x20, 0x60], "32(\"\")"),
+            (&[0xf4, 0xf5, 0xf6, 0xf7], "false, true, null, undefined"),
+            (&[0xf0, 0xf8, 0xff], "simple(16), simple(255)"),
+            (&[0xf9, 0x3e, 0x00], "1.5"),
+            (&[0xf9, 0x3c, 0x00], "1.0"),
+            (&[0xfa, 0x47, 0xc3, 0x50, 0x00], "100000.0"),
+            (
+                &[0xfb, 0x7e, 0x37, 0xe4, 0x3c, 0x88, 0x00, 0x75, 0x9c],
+                "1e300",
+            ),
+            (&[0xf9, 0x7c, 0x00, 0xf9, 0xfc, 0x00], "Infinity, -Infinity"),
+            (&[0xf9, 0x7e, 0x00], "NaN"),
+            (&[], ""),
+        ] {
+            assert_eq!(Diag::new(input).to_string(), expected, "{input:02x?}");
+        }
+    }
+
+    #[test]
+    fn diag_pretty() {
+        // {1: [2, h'03', []], "a": {_ }}
+        let input = [
+            0xa2, 0x01, 0x83, 0x02, 0x41, 0x03, 0x80, 0x61, b'a', 0xbf, 0xff,
+        ];
+        let expected = "{\n  1: [\n    2,\n    h'03',\n    []\n  ],\n  \"a\": {_ }\n}";
+        assert_eq!(Diag::new(&input).pretty(true).to_string(), expected);
+        assert_eq!(format!("{:#}", Diag::new(&input)), expected);
+    }
+
+    #[test]
+    fn diag_malformed() {
+        assert_eq!(
+            Diag::new(&[0x82, 0x01]).to_string(),
+            "[1, <Hit the end of buffer, expected more data>"
+        );
+        assert_eq!(
+            Diag::new(&[0x82, 0x01]).write_to(&mut String::new()),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            Diag::new(&[0x5f, 0x61, b'a', 0xff]).write_to(&mut String::new()),
+            Err(Error::DeserializeBadEncoding)
+        );
+        assert_eq!(
+            Diag::new(&[0x81; 64]).write_to(&mut String::new()),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+
+        struct Full;
+        impl core::fmt::Write for Full {
+            fn write_str(&mut self, _: &str) -> core::fmt::Result {
+                Err(core::fmt::Error)
+            }
+        }
+        assert_eq!(
+            Diag::new(&[0x01]).write_to(&mut Full),
+            Err(Error::SerializeBufferFull)
+        );
+    }
+}
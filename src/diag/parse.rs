@@ -0,0 +1,659 @@
+//! Parser for diagnostic notation.
+
+use crate::de::MAX_DEPTH;
+use crate::error::{Error, Result};
+use crate::ser::{Encoder, NullWriter, SliceWriter, Writer, WrittenWriter};
+
+/// Parse diagnostic notation into a buffer, returning the encoded CBOR data.
+///
+/// ```
+/// let mut buf = [0u8; 32];
+/// let cbor = cbor_smol::diag::parse(r#"[1, {"a": h'00'}, 1(1363896240)]"#, &mut buf).unwrap();
+/// assert_eq!(
+///     cbor,
+///     &[0x83, 0x01, 0xa1, 0x61, b'a', 0x41, 0x00, 0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]
+/// );
+/// ```
+pub fn parse<'a>(text: &str, buffer: &'a mut [u8]) -> Result<&'a [u8]> {
+    let mut writer = SliceWriter::new(buffer);
+    parse_to(text, &mut writer)?;
+    Ok(writer.into_written())
+}
+
+/// Parse diagnostic notation into a `Writer`, returning the amount of bytes written.
+///
+/// The supported syntax is the one of RFC 8949 §8 and RFC 8610 Appendix G:
+///
+/// - integers in decimal, or in hexadecimal, octal or binary with a `0x`, `0o` or `0b` prefix
+/// - floats with a decimal point or an exponent, `Infinity`, `-Infinity` and `NaN`
+/// - text strings `"..."` with JSON escapes
+/// - byte strings `h'...'`, `b64'...'` (base64 or base64url, padding is optional) and `'...'`
+/// - indefinite-length strings `(_ h'01', h'02')`, `''_` and `""_`
+/// - arrays `[...]` and maps `{...}`, indefinite-length with `[_ ...]` and `{_ ...}`
+/// - tags `1(...)`, `true`, `false`, `null`, `undefined` and `simple(...)`
+/// - comments `/ ... /`
+///
+/// Encoding indicators other than `_` for indefinite lengths are not supported, integers and
+/// floats are always written in their shortest form.  Several items separated by commas are
+/// written as a CBOR Sequence.
+pub fn parse_to<W: Writer>(text: &str, writer: W) -> Result<usize> {
+    let mut encoder = Encoder::new(WrittenWriter { writer, written: 0 });
+    let mut parser = Parser {
+        input: text.as_bytes(),
+        position: 0,
+        counting: false,
+    };
+    if !parser.at_end() {
+        loop {
+            parser.item(&mut encoder, 0)?;
+            if parser.at_end() {
+                break;
+            }
+            parser.expect(b',')?;
+        }
+    }
+    Ok(encoder.writer().written)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum StringKind {
+    /// `"..."`
+    Text,
+    /// `'...'`
+    Bytes,
+    /// `h'...'`
+    Hex,
+    /// `b64'...'`
+    Base64,
+}
+
+#[derive(Clone, Copy)]
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    /// Set for the pass that determines the length of an array or map, nested arrays and maps
+    /// do not need to be counted then.
+    counting: bool,
+}
+
+impl Parser<'_> {
+    fn counter(&self) -> Self {
+        Self {
+            counting: true,
+            ..*self
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        let c = self.peek().ok_or(Error::DiagInvalidSyntax)?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.position += 1,
+                Some(b'/') => {
+                    self.position += 1;
+                    while self.next()? != b'/' {}
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace().is_ok() && self.peek().is_none()
+    }
+
+    /// Consume `token` after optional whitespace, if it is present.
+    fn eat(&mut self, token: u8) -> Result<bool> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(token) {
+            self.position += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, token: u8) -> Result<()> {
+        if self.eat(token)? {
+            Ok(())
+        } else {
+            Err(Error::DiagInvalidSyntax)
+        }
+    }
+
+    fn looking_at(&self, keyword: &str) -> bool {
+        self.input[self.position..].starts_with(keyword.as_bytes())
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.looking_at(keyword);
+        if found {
+            self.position += keyword.len();
+        }
+        found
+    }
+
+    fn item<W: Writer>(&mut self, encoder: &mut Encoder<W>, depth: usize) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(Error::DeserializeDepthLimitExceeded);
+        }
+        self.skip_whitespace()?;
+        match self.peek().ok_or(Error::DiagInvalidSyntax)? {
+            b'[' => {
+                self.position += 1;
+                self.container(encoder, b']', false, depth + 1)
+            }
+            b'{' => {
+                self.position += 1;
+                self.container(encoder, b'}', true, depth + 1)
+            }
+            b'(' => {
+                self.position += 1;
+                self.expect(b'_')?;
+                self.indefinite_string(encoder)
+            }
+            // empty indefinite-length strings
+            b'"' if self.looking_at("\"\"_") => {
+                self.position += 3;
+                encoder.indefinite_text()?;
+                encoder.break_()
+            }
+            b'\'' if self.looking_at("''_") => {
+                self.position += 3;
+                encoder.indefinite_bytes()?;
+                encoder.break_()
+            }
+            b'"' => self.string(encoder, StringKind::Text),
+            b'\'' => self.string(encoder, StringKind::Bytes),
+            b'h' if self.looking_at("h'") => self.string(encoder, StringKind::Hex),
+            b'b' if self.looking_at("b64'") => self.string(encoder, StringKind::Base64),
+            b'0'..=b'9' | b'-' | b'+' | b'I' | b'N' => self.number(encoder, depth),
+            _ => {
+                if self.eat_keyword("true") {
+                    encoder.bool(true)
+                } else if self.eat_keyword("false") {
+                    encoder.bool(false)
+                } else if self.eat_keyword("null") {
+                    encoder.null()
+                } else if self.eat_keyword("undefined") {
+                    encoder.simple(23)
+                } else if self.eat_keyword("simple(") {
+                    let value = self.unsigned()?;
+                    self.expect(b')')?;
+                    encoder.simple(u8::try_from(value).map_err(|_| Error::DiagInvalidSyntax)?)
+                } else {
+                    Err(Error::DiagInvalidSyntax)
+                }
+            }
+        }
+    }
+
+    /// An array or map after the opening bracket.
+    fn container<W: Writer>(
+        &mut self,
+        encoder: &mut Encoder<W>,
+        close: u8,
+        map: bool,
+        depth: usize,
+    ) -> Result<()> {
+        if self.eat(b'_')? {
+            if map {
+                encoder.indefinite_map()?;
+            } else {
+                encoder.indefinite_array()?;
+            }
+            self.items(encoder, close, map, depth)?;
+            encoder.break_()
+        } else {
+            // parse the items once without writing them to determine the length
+            let len = if self.counting {
+                0
+            } else {
+                self.counter()
+                    .items(&mut Encoder::new(NullWriter::default()), close, map, depth)?
+            };
+            if map {
+                encoder.map_header(len)?;
+            } else {
+                encoder.array_header(len)?;
+            }
+            self.items(encoder, close, map, depth)?;
+            Ok(())
+        }
+    }
+
+    /// Parse comma-separated items up to `close`, returning the number of items.
+    fn items<W: Writer>(
+        &mut self,
+        encoder: &mut Encoder<W>,
+        close: u8,
+        map: bool,
+        depth: usize,
+    ) -> Result<usize> {
+        let mut len = 0;
+        while !self.eat(close)? {
+            if len > 0 {
+                self.expect(b',')?;
+                // trailing comma
+                if self.eat(close)? {
+                    break;
+                }
+            }
+            self.item(encoder, depth)?;
+            if map {
+                self.expect(b':')?;
+                self.item(encoder, depth)?;
+            }
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// The chunks of an indefinite-length string after `(_`.
+    fn indefinite_string<W: Writer>(&mut self, encoder: &mut Encoder<W>) -> Result<()> {
+        let mut text = None;
+        while !self.eat(b')')? {
+            if text.is_some() {
+                self.expect(b',')?;
+            }
+            self.skip_whitespace()?;
+            let is_text = self.peek() == Some(b'"');
+            match text {
+                None if is_text => encoder.indefinite_text()?,
+                None => encoder.indefinite_bytes()?,
+                Some(text) if text != is_text => return Err(Error::DiagInvalidSyntax),
+                Some(_) => {}
+            }
+            text = Some(is_text);
+            match self.peek() {
+                Some(b'"') => self.string(encoder, StringKind::Text)?,
+                Some(b'\'') => self.string(encoder, StringKind::Bytes)?,
+                Some(b'h') => self.string(encoder, StringKind::Hex)?,
+                Some(b'b') => self.string(encoder, StringKind::Base64)?,
+                _ => return Err(Error::DiagInvalidSyntax),
+            }
+        }
+        if text.is_none() {
+            // use ''_ or ""_ for empty indefinite-length strings
+            return Err(Error::DiagInvalidSyntax);
+        }
+        encoder.break_()
+    }
+
+    fn string<W: Writer>(&mut self, encoder: &mut Encoder<W>, kind: StringKind) -> Result<()> {
+        let mut len = 0;
+        self.counter().string_content(kind, |chunk| {
+            len += chunk.len();
+            Ok(())
+        })?;
+        if kind == StringKind::Text {
+            encoder.text_header(len)?;
+        } else {
+            encoder.bytes_header(len)?;
+        }
+        self.string_content(kind, |chunk| encoder.raw(chunk))
+    }
+
+    /// Decode a quoted string, passing its content to `emit` in chunks.
+    fn string_content(
+        &mut self,
+        kind: StringKind,
+        mut emit: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let quote = match kind {
+            StringKind::Text => b'"',
+            StringKind::Bytes => b'\'',
+            StringKind::Hex => {
+                self.position += 1;
+                b'\''
+            }
+            StringKind::Base64 => {
+                self.position += 3;
+                b'\''
+            }
+        };
+        if self.next()? != quote {
+            return Err(Error::DiagInvalidSyntax);
+        }
+        match kind {
+            StringKind::Text | StringKind::Bytes => loop {
+                let c = self.next()?;
+                if c == quote {
+                    return Ok(());
+                } else if c == b'\\' {
+                    let c = self.escape()?;
+                    emit(c.encode_utf8(&mut [0; 4]).as_bytes())?;
+                } else {
+                    emit(&[c])?;
+                }
+            },
+            StringKind::Hex => {
+                let mut high = None;
+                loop {
+                    let c = self.next()?;
+                    if c == quote {
+                        break;
+                    }
+                    if c.is_ascii_whitespace() {
+                        continue;
+                    }
+                    let digit = match c {
+                        b'0'..=b'9' => c - b'0',
+                        b'a'..=b'f' => c - b'a' + 10,
+                        b'A'..=b'F' => c - b'A' + 10,
+                        _ => return Err(Error::DiagInvalidSyntax),
+                    };
+                    match high.take() {
+                        Some(high) => emit(&[high << 4 | digit])?,
+                        None => high = Some(digit),
+                    }
+                }
+                if high.is_some() {
+                    return Err(Error::DiagInvalidSyntax);
+                }
+                Ok(())
+            }
+            StringKind::Base64 => {
+                let mut bits = 0u32;
+                let mut count = 0;
+                let mut padding = false;
+                loop {
+                    let c = self.next()?;
+                    let value = match c {
+                        _ if c == quote => break,
+                        b'=' => {
+                            padding = true;
+                            continue;
+                        }
+                        _ if padding => return Err(Error::DiagInvalidSyntax),
+                        b'A'..=b'Z' => c - b'A',
+                        b'a'..=b'z' => c - b'a' + 26,
+                        b'0'..=b'9' => c - b'0' + 52,
+                        b'+' | b'-' => 62,
+                        b'/' | b'_' => 63,
+                        _ => return Err(Error::DiagInvalidSyntax),
+                    };
+                    bits = bits << 6 | u32::from(value);
+                    count += 1;
+                    if count == 4 {
+                        emit(&bits.to_be_bytes()[1..])?;
+                        bits = 0;
+                        count = 0;
+                    }
+                }
+                match count {
+                    0 => Ok(()),
+                    2 => emit(&[(bits >> 4) as u8]),
+                    3 => emit(&(bits >> 2).to_be_bytes()[2..]),
+                    _ => Err(Error::DiagInvalidSyntax),
+                }
+            }
+        }
+    }
+
+    /// Decode an escape sequence after the backslash.
+    fn escape(&mut self) -> Result<char> {
+        Ok(match self.next()? {
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'u' => {
+                let high = self.hex4()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    // surrogate pair
+                    if !self.eat_keyword("\\u") {
+                        return Err(Error::DiagInvalidSyntax);
+                    }
+                    let low = self.hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(Error::DiagInvalidSyntax);
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or(Error::DiagInvalidSyntax)?
+            }
+            c @ (b'"' | b'\'' | b'\\' | b'/') => c.into(),
+            _ => return Err(Error::DiagInvalidSyntax),
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self
+            .input
+            .get(self.position..self.position + 4)
+            .ok_or(Error::DiagInvalidSyntax)?;
+        self.position += 4;
+        let digits = core::str::from_utf8(digits).map_err(|_| Error::DiagInvalidSyntax)?;
+        u32::from_str_radix(digits, 16).map_err(|_| Error::DiagInvalidSyntax)
+    }
+
+    /// The characters of a number, up to the next delimiter.
+    fn number_token(&mut self) -> &str {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            let exponent_sign = matches!(c, b'+' | b'-')
+                && self.position > start
+                && matches!(self.input[self.position - 1], b'e' | b'E');
+            if c.is_ascii_alphanumeric() || c == b'.' || exponent_sign {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        // only ASCII characters were consumed
+        core::str::from_utf8(&self.input[start..self.position]).unwrap_or_default()
+    }
+
+    fn unsigned(&mut self) -> Result<u128> {
+        self.skip_whitespace()?;
+        let token = self.number_token();
+        let (digits, radix) = match token.get(..2) {
+            Some("0x" | "0X") => (&token[2..], 16),
+            Some("0o" | "0O") => (&token[2..], 8),
+            Some("0b" | "0B") => (&token[2..], 2),
+            _ => (token, 10),
+        };
+        if digits.starts_with('+') {
+            return Err(Error::DiagInvalidSyntax);
+        }
+        u128::from_str_radix(digits, radix).map_err(|_| Error::DiagInvalidSyntax)
+    }
+
+    /// An integer, float or tag.
+    fn number<W: Writer>(&mut self, encoder: &mut Encoder<W>, depth: usize) -> Result<()> {
+        let negative = self.peek() == Some(b'-');
+        let signed = negative || self.peek() == Some(b'+');
+        if signed {
+            self.position += 1;
+        }
+        let start = self.position;
+        if self.eat_keyword("Infinity") {
+            return encoder.float(if negative {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            });
+        }
+        if self.eat_keyword("NaN") {
+            return encoder.float(f64::NAN);
+        }
+
+        let token = self.number_token();
+        let is_float = !token.starts_with("0x")
+            && !token.starts_with("0X")
+            && token.bytes().any(|c| matches!(c, b'.' | b'e' | b'E'));
+        if is_float {
+            if !token.starts_with(|c: char| c.is_ascii_digit()) {
+                return Err(Error::DiagInvalidSyntax);
+            }
+            let value: f64 = token.parse().map_err(|_| Error::DiagInvalidSyntax)?;
+            return encoder.float(if negative { -value } else { value });
+        }
+
+        self.position = start;
+        let value = self.unsigned()?;
+        if self.peek() == Some(b'(') {
+            if signed {
+                return Err(Error::DiagInvalidSyntax);
+            }
+            self.position += 1;
+            encoder.tag(u64::try_from(value).map_err(|_| Error::DiagInvalidSyntax)?)?;
+            self.item(encoder, depth + 1)?;
+            return self.expect(b')');
+        }
+        let too_large = |_| Error::DiagInvalidSyntax;
+        if negative && value > 0 {
+            encoder.negint(u64::try_from(value - 1).map_err(too_large)?)
+        } else {
+            encoder.uint(u64::try_from(value).map_err(too_large)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_to};
+    use crate::diag::Diag;
+    use crate::ser::SliceWriter;
+    use crate::Error;
+
+    fn parse_vec(text: &str) -> Result<Vec<u8>, Error> {
+        let mut buf = [0u8; 256];
+        parse(text, &mut buf).map(<[u8]>::to_vec)
+    }
+
+    #[test]
+    fn parse_items() {
+        for (text, expected) in [
+            ("0", &[0x00][..]),
+            ("23", &[0x17]),
+            ("-1", &[0x20]),
+            ("-0", &[0x00]),
+            ("+5", &[0x05]),
+            ("0x1f4", &[0x19, 0x01, 0xf4]),
+            ("-0b11", &[0x22]),
+            ("0o17", &[0x0f]),
+            (
+                "18446744073709551615",
+                &[0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (
+                "-18446744073709551616",
+                &[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            ("1.5", &[0xf9, 0x3e, 0x00]),
+            ("-4.0", &[0xf9, 0xc4, 0x00]),
+            (
+                "1e300",
+                &[0xfb, 0x7e, 0x37, 0xe4, 0x3c, 0x88, 0x00, 0x75, 0x9c],
+            ),
+            ("1.0E+5", &[0xfa, 0x47, 0xc3, 0x50, 0x00]),
+            ("-2.5e-1", &[0xf9, 0xb4, 0x00]),
+            ("Infinity", &[0xf9, 0x7c, 0x00]),
+            ("-Infinity", &[0xf9, 0xfc, 0x00]),
+            ("NaN", &[0xf9, 0x7e, 0x00]),
+            ("false", &[0xf4]),
+            ("true", &[0xf5]),
+            ("null", &[0xf6]),
+            ("undefined", &[0xf7]),
+            ("simple(16)", &[0xf0]),
+            ("simple(255)", &[0xf8, 0xff]),
+            ("h''", &[0x40]),
+            ("h'01 AB ff'", &[0x43, 0x01, 0xab, 0xff]),
+            ("b64'AQID'", &[0x43, 0x01, 0x02, 0x03]),
+            ("b64'-_8'", &[0x42, 0xfb, 0xff]),
+            ("b64'+/8='", &[0x42, 0xfb, 0xff]),
+            ("b64'AQ=='", &[0x41, 0x01]),
+            ("'a\\''", &[0x42, b'a', b'\'']),
+            ("\"\"", &[0x60]),
+            (
+                r#""a\"\\\n\u00fc\ud83d\ude00""#,
+                &[
+                    0x6a, b'a', b'"', b'\\', b'\n', 0xc3, 0xbc, 0xf0, 0x9f, 0x98, 0x80,
+                ],
+            ),
+            ("\"ü\"", &[0x62, 0xc3, 0xbc]),
+            ("''_", &[0x5f, 0xff]),
+            ("\"\"_", &[0x7f, 0xff]),
+            (
+                "(_ h'01', '\\u0002')",
+                &[0x5f, 0x41, 0x01, 0x41, 0x02, 0xff],
+            ),
+            ("(_ \"a\", \"b\")", &[0x7f, 0x61, b'a', 0x61, b'b', 0xff]),
+            ("[]", &[0x80]),
+            ("[_ ]", &[0x9f, 0xff]),
+            ("[1, [2, 3], []]", &[0x83, 0x01, 0x82, 0x02, 0x03, 0x80]),
+            ("[1, 2,]", &[0x82, 0x01, 0x02]),
+            ("{}", &[0xa0]),
+            ("{_ 1: [_ ]}", &[0xbf, 0x01, 0x9f, 0xff, 0xff]),
+            ("{1: 2, \"a\": {}}", &[0xa2, 0x01, 0x02, 0x61, b'a', 0xa0]),
+            ("24(h'01')", &[0xd8, 0x18, 0x41, 0x01]),
+            ("1(0(\"\"))", &[0xc1, 0xc0, 0x60]),
+            (" / comment / [ 1 /one/ ]\n", &[0x81, 0x01]),
+            ("1, \"a\"", &[0x01, 0x61, b'a']),
+            ("", &[]),
+        ] {
+            assert_eq!(parse_vec(text).as_deref(), Ok(expected), "{text}");
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for text in [
+            "[1",
+            "[1 2]",
+            "[,]",
+            "{1}",
+            "{1: }",
+            "1 2",
+            "01x",
+            "18446744073709551616",
+            "-18446744073709551617",
+            "0x",
+            "+1(2)",
+            "-1(2)",
+            "h'1'",
+            "h'0g'",
+            "b64'A'",
+            "\"\\x\"",
+            "\"\\ud800\"",
+            "\"a",
+            "(_ )",
+            "(_ h'', \"\")",
+            "simple(256)",
+            "nil",
+            "/ unterminated",
+        ] {
+            assert_eq!(parse_vec(text), Err(Error::DiagInvalidSyntax), "{text}");
+        }
+        assert_eq!(parse_vec("simple(24)"), Err(Error::SerializeBadSimple));
+        assert_eq!(
+            parse_vec(&"[".repeat(64)),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+
+        let mut buf = [0u8; 2];
+        assert_eq!(parse("[1, 2]", &mut buf), Err(Error::SerializeBufferFull));
+    }
+
+    #[test]
+    fn parse_roundtrip() {
+        let text = r#"{1: h'0102', "rk": [_ true, -1.5, 2(h'0100')], "x": (_ "a", "b")}"#;
+        let mut buf = [0u8; 64];
+        let written = parse_to(text, SliceWriter::new(&mut buf)).unwrap();
+        assert_eq!(Diag::new(&buf[..written]).to_string(), text);
+    }
+}
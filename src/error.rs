@@ -16,6 +16,9 @@ pub enum Error {
     NotYetImplemented,
     /// The serialize buffer is full
     SerializeBufferFull,
+    /// Tried to serialize a reserved simple value (24 to 31), or false, true, null or undefined
+    /// as a `Simple`
+    SerializeBadSimple,
     // /// The length of a sequence must be known
     // SerializeSeqLengthUnknown,
     /// Hit the end of buffer, expected more data
@@ -24,8 +27,8 @@ pub enum Error {
     // DeserializeBadVarint,
     /// Found a bool that wasn't 0xf4 or 0xf5
     DeserializeBadBool,
-    // /// Found an invalid unicode char
-    // DeserializeBadChar,
+    /// Expected a text string holding exactly one char
+    DeserializeBadChar,
     /// Tried to parse invalid utf-8
     DeserializeBadUtf8,
     // /// Found an Option discriminant that wasn't 0 or 1
@@ -33,8 +36,14 @@ pub enum Error {
     // /// Found an enum discriminant that was > u32::max_value()
     /// Could not parse an enum
     DeserializeBadEnum,
-    // /// The original data was not well encoded
-    // DeserializeBadEncoding,
+    /// The original data was not well encoded
+    DeserializeBadEncoding,
+    /// Found an indefinite-length item where it is not allowed
+    DeserializeIndefiniteLength,
+    /// Map keys are not in the deterministic order, or not unique
+    DeserializeUnsortedKeys,
+    /// The content of a tag does not have the expected type
+    DeserializeBadTag,
     /// Expected a different major type
     DeserializeBadMajor,
     /// Expected a i8, was too large
@@ -53,8 +62,40 @@ pub enum Error {
     DeserializeBadU32,
     /// Expected a u64
     DeserializeBadU64,
+    /// Expected a i128, was too large
+    DeserializeBadI128,
+    /// Expected a u128
+    DeserializeBadU128,
+    /// Expected a floating-point number that fits the deserialized type
+    DeserializeBadFloat,
     /// Expected a NULL marker
     DeserializeExpectedNull,
+    /// Expected an undefined marker
+    DeserializeExpectedUndefined,
+    /// Expected a simple value that is not reserved, and for a `Simple` not false, true, null
+    /// or undefined
+    DeserializeBadSimple,
+    /// An array or map had more items than the deserialized type expected
+    DeserializeTrailingItems,
+    /// An array, tuple or byte string did not have the expected length, as reported by any
+    /// visitor through `serde::de::Error::invalid_length`
+    DeserializeBadLength,
+    /// An array, map or byte string had more items than the deserialized container can hold
+    ///
+    /// The number of items is part of the input, and the capacity part of the deserialized type.
+    DeserializeCapacityExceeded,
+    /// Items are nested too deeply
+    DeserializeDepthLimitExceeded,
+    /// The input exceeds one of the configured `de::Limits`
+    DeserializeLimitExceeded,
+    /// Found data after the end of the item
+    DeserializeTrailingData,
+    /// A CBOR Sequence ended in the middle of an item
+    DeserializeIncompleteItem,
+    /// A CBOR Sequence did not start with the expected magic number
+    DeserializeBadMagic,
+    /// Could not parse diagnostic notation
+    DiagInvalidSyntax,
     /// Inexistent slice-to-array cast error. Used here to avoid calling unwrap.
     InexistentSliceToArrayError,
     /// Value may be valid, but not encoded in minimal way
@@ -79,18 +120,22 @@ impl Display for Error {
                     "This is a feature that cbor-smol intends to support, but does not yet"
                 }
                 SerializeBufferFull => "The serialize buffer is full",
+                SerializeBadSimple => "Tried to serialize a reserved simple value",
                 // SerializeSeqLengthUnknown => "The length of a sequence must be known",
                 DeserializeUnexpectedEnd => "Hit the end of buffer, expected more data",
                 // DeserializeBadVarint => {
                 //     "Found a varint that didn't terminate. Is the usize too big for this platform?"
                 // }
                 DeserializeBadBool => "Found a bool that wasn't 0xf4 or 0xf5",
-                // DeserializeBadChar => "Found an invalid unicode char",
+                DeserializeBadChar => "Expected a text string holding exactly one char",
                 DeserializeBadUtf8 => "Tried to parse invalid utf-8",
                 // DeserializeBadOption => "Found an Option discriminant that wasn't 0 or 1",
                 // DeserializeBadEnum => "Found an enum discriminant that was > u32::max_value()",
                 DeserializeBadEnum => "Could not parse an enum",
-                // DeserializeBadEncoding => "The original data was not well encoded",
+                DeserializeBadEncoding => "The original data was not well encoded",
+                DeserializeIndefiniteLength => "Found an unexpected indefinite-length item",
+                DeserializeUnsortedKeys => "Map keys are not sorted or not unique",
+                DeserializeBadTag => "The content of a tag does not have the expected type",
                 DeserializeBadI8 => "Expected a i8",
                 DeserializeBadI16 => "Expected a i16",
                 DeserializeBadI32 => "Expected a i32",
@@ -100,7 +145,21 @@ impl Display for Error {
                 DeserializeBadU16 => "Expected a u16",
                 DeserializeBadU32 => "Expected a u32",
                 DeserializeBadU64 => "Expected a u64",
+                DeserializeBadI128 => "Expected a i128",
+                DeserializeBadU128 => "Expected a u128",
+                DeserializeBadFloat => "Expected a float",
                 DeserializeExpectedNull => "Expected 0xf6",
+                DeserializeExpectedUndefined => "Expected 0xf7",
+                DeserializeBadSimple => "Expected a simple value that is not reserved",
+                DeserializeTrailingItems => "Found more items than expected in an array or map",
+                DeserializeBadLength => "Found an array or byte string of unexpected length",
+                DeserializeCapacityExceeded => "Found more items than the container can hold",
+                DeserializeDepthLimitExceeded => "Items are nested too deeply",
+                DeserializeLimitExceeded => "The input exceeds a configured limit",
+                DeserializeTrailingData => "Found data after the end of the item",
+                DeserializeIncompleteItem => "The sequence ended in the middle of an item",
+                DeserializeBadMagic => "The sequence did not start with the magic number",
+                DiagInvalidSyntax => "Could not parse diagnostic notation",
                 InexistentSliceToArrayError => "",
                 DeserializeNonMinimal => "Value may be valid, but not encoded in minimal way",
                 SerdeSerCustom => "Serde Serialization Error",
@@ -127,17 +186,11 @@ impl serde::de::Error for Error {
     {
         // TODO: Would be helpful to log this to system logger
         // This shows e.g.
-        // - missing fields
         // - expected sequence, received X
         // - etc.
         //
-        // Particularly helpful would be better errors when receiving
-        // structures are undersized.
-        //
-        // E.g. if there is a `Bytes<N>` and more than N bytes are delivered,
-        // currently the error _msg: T is:
-        //
-        // `invalid length 297, expected a sequence`
+        // Missing fields and invalid lengths have their own variants, see
+        // below.
         //
         info_now!("deser error: {}", &msg);
         Error::SerdeDeCustom
@@ -146,6 +199,39 @@ impl serde::de::Error for Error {
         info_now!("deser missing: {}", field);
         Error::SerdeMissingField
     }
+    fn invalid_length(len: usize, exp: &dyn serde::de::Expected) -> Self {
+        info_now!("deser invalid length: {}, expected {}", len, exp);
+        Error::DeserializeBadLength
+    }
 }
 
 impl serde::ser::StdError for Error {}
+
+/// An error of [`cbor_serialize_to_with_writer_error`](crate::cbor_serialize_to_with_writer_error)
+/// that keeps the original error of the `Writer`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SerError<E> {
+    /// The object could not be serialized
+    Cbor(Error),
+    /// The `Writer` failed
+    Io(E),
+}
+
+impl<E: Display> Display for SerError<E> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            SerError::Cbor(err) => err.fmt(f),
+            SerError::Io(err) => write!(f, "The writer failed: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for SerError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SerError::Cbor(err) => Some(err),
+            SerError::Io(err) => Some(err),
+        }
+    }
+}
@@ -1,40 +1,158 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 
 #[macro_use]
 extern crate delog;
 generate_macros!();
 
+pub mod bytes;
 pub(crate) mod consts;
 pub mod de;
+pub mod diag;
 pub mod error;
+pub mod max_size;
+pub mod raw;
+pub mod sequence;
 pub mod ser;
+pub mod simple;
+pub mod token;
+#[cfg(feature = "std")]
+pub mod transcode;
+pub mod validation;
 
-pub use error::{Error, Result};
-use ser::Writer;
+#[cfg(feature = "derive")]
+pub use cbor_smol_derive::CborMaxSize;
+pub use error::{Error, Result, SerError};
+pub use max_size::CborMaxSize;
+pub use raw::RawCbor;
+use ser::{SeekableWriter as _, Writer};
+pub use simple::{Simple, Undefined};
+pub use validation::{validate, ValidationOptions};
 
 /// Serialize an object to a `Writer`
 ///
-/// Returns the amount of bytes written to the writer
+/// Returns the amount of bytes written to the writer.  If serialization fails and the writer is
+/// a [`SeekableWriter`](ser::SeekableWriter), such as a `heapless::Vec`, the data written so
+/// far is discarded.
 pub fn cbor_serialize_to<T: ?Sized + serde::Serialize, W: Writer>(
     object: &T,
-    writer: W,
+    mut writer: W,
 ) -> Result<usize> {
+    let checkpoint = writer.seekable().map(|writer| writer.checkpoint());
     let mut serializer = ser::Serializer::new(writer);
-    object.serialize(&mut serializer)?;
+    let result = object.serialize(&mut serializer);
+    rollback_on_error(&mut serializer, checkpoint, &result);
+    result?;
     Ok(serializer.written())
 }
 
+/// Discard the data written by a failed serializer.
+fn rollback_on_error<W: Writer, T, E>(
+    serializer: &mut ser::Serializer<W>,
+    checkpoint: Option<ser::Checkpoint>,
+    result: &core::result::Result<T, E>,
+) {
+    if let (Some(checkpoint), Err(_)) = (checkpoint, result) {
+        if let Some(writer) = serializer.seekable() {
+            writer.rollback(checkpoint);
+        }
+    }
+}
+
+/// Serialize an object to a `Writer`, returning the original error if the `Writer` fails
+///
+/// Returns the amount of bytes written to the writer.  Like [`cbor_serialize_to`], this
+/// discards the data written so far if serialization fails.
+pub fn cbor_serialize_to_with_writer_error<T: ?Sized + serde::Serialize, W: Writer>(
+    object: &T,
+    writer: W,
+) -> core::result::Result<usize, SerError<W::Error>> {
+    let mut writer = ser::ErrorWriter::new(writer);
+    let checkpoint = writer.seekable().map(|writer| writer.checkpoint());
+    let mut serializer = ser::Serializer::new(writer);
+    let result = object.serialize(&mut serializer);
+    rollback_on_error(&mut serializer, checkpoint, &result);
+    let written = serializer.written();
+    match (result, serializer.into_inner().into_error()) {
+        (_, Some(err)) => Err(SerError::Io(err)),
+        (Err(err), None) => Err(SerError::Cbor(err)),
+        (Ok(()), None) => Ok(written),
+    }
+}
+
+/// Serialize as many items as fit into a buffer, as a definite-length array
+///
+/// Items are added in order until the next one does not fit, leaving out the remaining items.
+/// Returns the serialized array and the number of items it holds, for example to paginate a
+/// list.  Errors other than `Error::SerializeBufferFull` are returned unchanged.
+pub fn cbor_serialize_seq_fitting<I>(items: I, buffer: &mut [u8]) -> Result<(&[u8], usize)>
+where
+    I: IntoIterator,
+    I::Item: serde::Serialize,
+{
+    let capacity = buffer.len();
+    let mut writer = ser::SliceWriter::new(buffer);
+    // a placeholder for the header, replaced at the end
+    writer.write_all(&[0])?;
+    let mut count = 0;
+    for item in items {
+        let checkpoint = writer.checkpoint();
+        let result = serde::Serialize::serialize(&item, &mut ser::Serializer::new(&mut writer));
+        // the header must still fit when it grows
+        let header_growth = max_size::header_size(count + 1) - 1;
+        match result {
+            Ok(()) if writer.written().len() + header_growth <= capacity => count += 1,
+            Ok(()) | Err(Error::SerializeBufferFull) => {
+                writer.rollback(checkpoint);
+                break;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    let mut header = [0; 9];
+    let mut header_writer = ser::SliceWriter::new(&mut header);
+    ser::Encoder::new(&mut header_writer).array_header(count)?;
+    writer.splice(0, 1, header_writer.written())?;
+    Ok((writer.into_written(), count))
+}
+
+/// Compute the amount of bytes that serializing an object would produce
+///
+/// No buffer is required: the serializer runs against a `Writer` that only counts.  This is the
+/// size of the output for any [`SeekableWriter`](ser::SeekableWriter), including all writers of
+/// this crate.  Custom writers that are not seekable get arrays and maps of unknown length with
+/// an indefinite length, which can take more bytes.
+pub fn serialized_size<T: ?Sized + serde::Serialize>(object: &T) -> Result<usize> {
+    cbor_serialize_to(object, ser::NullWriter::default())
+}
+
 // kudos to postcard, this is much nicer than returning size
 pub fn cbor_serialize<'a, T: ?Sized + serde::Serialize>(
     object: &T,
     buffer: &'a mut [u8],
 ) -> Result<&'a [u8]> {
-    let mut buf = &mut *buffer;
-    let written = cbor_serialize_to(object, &mut buf)?;
-    Ok(&buffer[..written])
+    let mut writer = ser::SliceWriter::new(buffer);
+    cbor_serialize_to(object, &mut writer)?;
+    Ok(writer.into_written())
 }
 
 pub fn cbor_deserialize<'de, T: serde::Deserialize<'de>>(buffer: &'de [u8]) -> Result<T> {
     // cortex_m_semihosting::hprintln!("deserializing {:?}", buffer).ok();
     de::from_bytes(buffer)
 }
+
+/// Determine the length of the CBOR item at the start of a buffer
+///
+/// See [`de::item_len`].
+pub fn item_len(buffer: &[u8]) -> core::result::Result<usize, de::ItemLenError> {
+    de::item_len(buffer)
+}
+
+/// Deserialize an object from a buffer into an existing value
+///
+/// See [`de::from_bytes_in_place`] for when this avoids building the object on the stack.
+pub fn deserialize_in_place<'de, T: serde::Deserialize<'de>>(
+    buffer: &'de [u8],
+    place: &mut T,
+) -> Result<()> {
+    de::from_bytes_in_place(buffer, place)
+}
@@ -0,0 +1,218 @@
+//! Compile-time upper bounds for the serialized size of types.
+//!
+//! [`CborMaxSize::MAX_SIZE`] is the largest number of bytes the cbor-smol serializer can produce
+//! for a value of the implementing type, so it can be used to size buffers at compile time:
+//!
+//! ```
+//! use cbor_smol::CborMaxSize;
+//!
+//! let mut buf = [0u8; <(u8, Option<u32>, [u16; 4])>::MAX_SIZE];
+//! let value = (7u8, Some(70_000u32), [1u16, 2, 300, 4]);
+//! assert!(cbor_smol::cbor_serialize(&value, &mut buf).is_ok());
+//! ```
+//!
+//! With the `derive` feature, `#[derive(CborMaxSize)]` implements the trait for structs and
+//! enums, taking into account the string keys of struct fields and the encoding of enum
+//! variants.
+
+use core::marker::PhantomData;
+
+/// A type with a known upper bound for its serialized size.
+pub trait CborMaxSize {
+    /// The maximum number of bytes that the serialization of a value of this type can take.
+    const MAX_SIZE: usize;
+}
+
+/// The size of the header (major type and argument) for an integer or length `value`.
+pub const fn header_size(value: usize) -> usize {
+    if value <= 23 {
+        1
+    } else if value <= u8::MAX as usize {
+        2
+    } else if value <= u16::MAX as usize {
+        3
+    } else if value as u64 <= u32::MAX as u64 {
+        5
+    } else {
+        9
+    }
+}
+
+/// The size of a text string, for example a struct field name used as a map key.
+pub const fn str_size(value: &str) -> usize {
+    header_size(value.len()) + value.len()
+}
+
+/// `core::cmp::max` for use in constants.
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! impl_max_size {
+    ($($ty:ty => $size:expr),* $(,)?) => {
+        $(
+            impl CborMaxSize for $ty {
+                const MAX_SIZE: usize = $size;
+            }
+        )*
+    };
+}
+
+impl_max_size! {
+    bool => 1,
+    () => 1,
+    u8 => 2,
+    u16 => 3,
+    u32 => 5,
+    u64 => 9,
+    usize => 9,
+    i8 => 2,
+    i16 => 3,
+    i32 => 5,
+    i64 => 9,
+    isize => 9,
+    // tag, byte string header and at most 16 bytes
+    i128 => 18,
+    u128 => 18,
+    f32 => 5,
+    f64 => 9,
+    // header plus at most four bytes of UTF-8
+    char => 5,
+    crate::Simple => 2,
+    crate::Undefined => 1,
+}
+
+impl<T: ?Sized> CborMaxSize for PhantomData<T> {
+    const MAX_SIZE: usize = 1;
+}
+
+impl<T: CborMaxSize + ?Sized> CborMaxSize for &T {
+    const MAX_SIZE: usize = T::MAX_SIZE;
+}
+
+impl<T: CborMaxSize + ?Sized> CborMaxSize for &mut T {
+    const MAX_SIZE: usize = T::MAX_SIZE;
+}
+
+impl<T: CborMaxSize> CborMaxSize for Option<T> {
+    const MAX_SIZE: usize = max(1, T::MAX_SIZE);
+}
+
+impl<T: CborMaxSize, const N: usize> CborMaxSize for [T; N] {
+    const MAX_SIZE: usize = header_size(N) + N * T::MAX_SIZE;
+}
+
+impl<const N: usize> CborMaxSize for crate::bytes::ByteArray<N> {
+    const MAX_SIZE: usize = header_size(N) + N;
+}
+
+macro_rules! impl_max_size_tuple {
+    ($($len:literal => ($($name:ident)+))+) => {
+        $(
+            impl<$($name: CborMaxSize),+> CborMaxSize for ($($name,)+) {
+                const MAX_SIZE: usize = header_size($len) $(+ $name::MAX_SIZE)+;
+            }
+        )+
+    };
+}
+
+impl_max_size_tuple! {
+    1 => (T0)
+    2 => (T0 T1)
+    3 => (T0 T1 T2)
+    4 => (T0 T1 T2 T3)
+    5 => (T0 T1 T2 T3 T4)
+    6 => (T0 T1 T2 T3 T4 T5)
+    7 => (T0 T1 T2 T3 T4 T5 T6)
+    8 => (T0 T1 T2 T3 T4 T5 T6 T7)
+}
+
+#[cfg(feature = "heapless-v0-7")]
+impl<T: CborMaxSize, const N: usize> CborMaxSize for heapless_v0_7::Vec<T, N> {
+    const MAX_SIZE: usize = header_size(N) + N * T::MAX_SIZE;
+}
+
+#[cfg(feature = "heapless-v0-7")]
+impl<const N: usize> CborMaxSize for heapless_v0_7::String<N> {
+    const MAX_SIZE: usize = header_size(N) + N;
+}
+
+#[cfg(feature = "heapless-v0-8")]
+impl<T: CborMaxSize, const N: usize> CborMaxSize for heapless_v0_8::Vec<T, N> {
+    const MAX_SIZE: usize = header_size(N) + N * T::MAX_SIZE;
+}
+
+#[cfg(feature = "heapless-v0-8")]
+impl<const N: usize> CborMaxSize for heapless_v0_8::String<N> {
+    const MAX_SIZE: usize = header_size(N) + N;
+}
+
+#[cfg(feature = "heapless-bytes-v0-3")]
+impl<const N: usize> CborMaxSize for heapless_bytes_v0_3::Bytes<N> {
+    const MAX_SIZE: usize = header_size(N) + N;
+}
+
+#[cfg(feature = "heapless-bytes-v0-4")]
+impl<const N: usize> CborMaxSize for heapless_bytes_v0_4::Bytes<N> {
+    const MAX_SIZE: usize = header_size(N) + N;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CborMaxSize;
+    use crate::cbor_serialize;
+
+    fn assert_tight<T: CborMaxSize + serde::Serialize>(value: &T) {
+        let mut buf = [0u8; 1024];
+        let ser = cbor_serialize(value, &mut buf).unwrap();
+        assert_eq!(ser.len(), T::MAX_SIZE);
+    }
+
+    #[test]
+    fn max_size_primitives() {
+        assert_tight(&true);
+        assert_tight(&());
+        assert_tight(&u8::MAX);
+        assert_tight(&u16::MAX);
+        assert_tight(&u32::MAX);
+        assert_tight(&u64::MAX);
+        assert_tight(&i8::MIN);
+        assert_tight(&i16::MIN);
+        assert_tight(&i32::MIN);
+        assert_tight(&i64::MIN);
+        assert_tight(&'🐝');
+        assert_tight(&Some(u32::MAX));
+        assert_tight(&[u16::MAX; 24]);
+        assert_tight(&(u8::MAX, i64::MIN, [true; 2]));
+    }
+
+    #[cfg_attr(
+        not(all(feature = "heapless-v0-8", feature = "heapless-bytes-v0-4")),
+        ignore = "Enable heapless-v0-8 and heapless-bytes-v0-4 features"
+    )]
+    #[test]
+    fn max_size_heapless() {
+        #[cfg(all(feature = "heapless-v0-8", feature = "heapless-bytes-v0-4"))]
+        {
+            let vec = heapless_v0_8::Vec::<u32, 30>::from_slice(&[u32::MAX; 30]).unwrap();
+            assert_tight(&vec);
+
+            let mut string = heapless_v0_8::String::<300>::new();
+            for _ in 0..75 {
+                string.push('🐝').unwrap();
+            }
+            assert_tight(&string);
+
+            let bytes = heapless_bytes_v0_4::Bytes::<256>::try_from(&[0x42; 256][..]).unwrap();
+            assert_tight(&bytes);
+        }
+        #[cfg(not(all(feature = "heapless-v0-8", feature = "heapless-bytes-v0-4")))]
+        {
+            panic!("This test must be run with the heapless-v0-8 and heapless-bytes-v0-4 features")
+        }
+    }
+}
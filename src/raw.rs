@@ -0,0 +1,132 @@
+//! Access to the encoded bytes of a CBOR item.
+
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::de::Deserializer as CborDeserializer;
+use crate::error::{Error, Result};
+
+/// The newtype struct name that the cbor-smol serializer and deserializer recognize for
+/// [`RawCbor`][].
+pub(crate) const RAW_CBOR_NAME: &str = "\0cbor_smol::RawCbor";
+
+/// The encoded bytes of exactly one complete CBOR item.
+///
+/// When deserialized, this borrows the bytes of the item from the input without decoding it,
+/// for example to verify a signature over the original encoding.  When serialized, the bytes
+/// are written unchanged.
+///
+/// This only works with the cbor-smol serializer and deserializer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RawCbor<'a>(&'a [u8]);
+
+impl<'a> RawCbor<'a> {
+    /// Check that `bytes` holds exactly one CBOR item and wrap it.
+    ///
+    /// Integers, lengths and tags must be in their shortest form.
+    pub fn from_slice(bytes: &'a [u8]) -> Result<Self> {
+        let mut deserializer = CborDeserializer::from_bytes(bytes);
+        deserializer.ignore()?;
+        if !deserializer.input.is_empty() {
+            return Err(Error::DeserializeTrailingData);
+        }
+        Ok(Self(bytes))
+    }
+
+    /// The encoded item.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Deserialize the encoded item.
+    pub fn decode<T: Deserialize<'a>>(&self) -> Result<T> {
+        crate::cbor_deserialize(self.0)
+    }
+}
+
+impl AsRef<[u8]> for RawCbor<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for RawCbor<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(RAW_CBOR_NAME, &RawBytes(self.0))
+    }
+}
+
+struct RawCborVisitor;
+
+impl<'de> Visitor<'de> for RawCborVisitor {
+    type Value = RawCbor<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a CBOR item")
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(
+        self,
+        v: &'de [u8],
+    ) -> core::result::Result<Self::Value, E> {
+        Ok(RawCbor(v))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawCbor<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(RAW_CBOR_NAME, RawCborVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RawCbor;
+    use crate::{cbor_deserialize, cbor_serialize, Error};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+    struct Response<'a> {
+        #[serde(borrow)]
+        auth_data: RawCbor<'a>,
+        signature: u8,
+    }
+
+    #[test]
+    fn raw_cbor() {
+        // {"auth_data": {1: [2, h'03'], 4: 1(5)}, "signature": 6}
+        let input = [
+            0xa2, 0x69, b'a', b'u', b't', b'h', b'_', b'd', b'a', b't', b'a', 0xa2, 0x01, 0x82,
+            0x02, 0x41, 0x03, 0x04, 0xc1, 0x05, 0x69, b's', b'i', b'g', b'n', b'a', b't', b'u',
+            b'r', b'e', 0x06,
+        ];
+        let response: Response = cbor_deserialize(&input).unwrap();
+        assert_eq!(response.auth_data.as_bytes(), &input[11..20]);
+        assert_eq!(response.signature, 6);
+
+        let mut buf = [0u8; 64];
+        let ser = cbor_serialize(&response, &mut buf).unwrap();
+        assert_eq!(ser, &input);
+
+        let raw = RawCbor::from_slice(&[0x82, 0x01, 0x61, b'a']).unwrap();
+        assert_eq!(raw.decode::<(u8, &str)>(), Ok((1, "a")));
+        assert_eq!(
+            RawCbor::from_slice(&[0x82, 0x01]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            RawCbor::from_slice(&[0x01, 0x02]),
+            Err(Error::DeserializeTrailingData)
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! CBOR Sequences ([RFC 8742](https://www.rfc-editor.org/rfc/rfc8742)).
+//!
+//! A CBOR Sequence is the concatenation of zero or more CBOR data items, without any framing
+//! in between.  This is convenient for append-only logs:
+//!
+//! ```
+//! use cbor_smol::sequence::{Sequence, SequenceWriter};
+//! use cbor_smol::ser::SliceWriter;
+//!
+//! let mut buf = [0u8; 16];
+//! let mut writer = SequenceWriter::new(SliceWriter::new(&mut buf));
+//! writer.push(&1u8).unwrap();
+//! writer.push(&1000u16).unwrap();
+//! let written = writer.written();
+//!
+//! let items: Result<Vec<u16>, _> = Sequence::new(&buf[..written]).collect();
+//! assert_eq!(items, Ok(vec![1, 1000]));
+//! ```
+
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::de::take_from_bytes;
+use crate::error::{Error, Result};
+use crate::ser::Writer;
+
+/// The media type for CBOR Sequences.
+pub const MEDIA_TYPE: &str = "application/cbor-seq";
+
+/// The "magic number" that can be used to identify a stored CBOR Sequence.
+///
+/// This is the tag 55800 enclosing the byte string `"BOR"`, as defined in
+/// [RFC 9277](https://www.rfc-editor.org/rfc/rfc9277).
+pub const MAGIC: [u8; 7] = [0xd9, 0xd9, 0xf8, 0x43, 0x42, 0x4f, 0x52];
+
+/// An iterator over the items of a CBOR Sequence.
+///
+/// Iteration ends when the input is exhausted.  If the input ends in the middle of an item,
+/// [`Error::DeserializeIncompleteItem`][] is returned.  After an error, the iterator does not
+/// return any more items.
+pub struct Sequence<'de, T> {
+    input: &'de [u8],
+    failed: bool,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<'de, T: Deserialize<'de>> Sequence<'de, T> {
+    pub fn new(input: &'de [u8]) -> Self {
+        Self {
+            input,
+            failed: false,
+            item: PhantomData,
+        }
+    }
+
+    /// Iterate over a CBOR Sequence that starts with the [`MAGIC`][] number.
+    pub fn with_magic(input: &'de [u8]) -> Result<Self> {
+        match input.strip_prefix(&MAGIC) {
+            Some(input) => Ok(Self::new(input)),
+            None if MAGIC.starts_with(input) => Err(Error::DeserializeIncompleteItem),
+            None => Err(Error::DeserializeBadMagic),
+        }
+    }
+
+    /// The part of the input that has not been deserialized yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.input
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Iterator for Sequence<'de, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.input.is_empty() {
+            return None;
+        }
+        match take_from_bytes(self.input) {
+            Ok((item, remaining)) => {
+                self.input = remaining;
+                Some(Ok(item))
+            }
+            Err(err) => {
+                self.failed = true;
+                if err == Error::DeserializeUnexpectedEnd {
+                    Some(Err(Error::DeserializeIncompleteItem))
+                } else {
+                    Some(Err(err))
+                }
+            }
+        }
+    }
+}
+
+/// Appends the items of a CBOR Sequence to a `Writer`.
+pub struct SequenceWriter<W> {
+    writer: W,
+    written: usize,
+}
+
+impl<W: Writer> SequenceWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, written: 0 }
+    }
+
+    /// Start a new CBOR Sequence with the [`MAGIC`][] number.
+    pub fn with_magic(mut writer: W) -> Result<Self> {
+        writer.write_all(&MAGIC).map_err(|e| e.into())?;
+        Ok(Self {
+            writer,
+            written: MAGIC.len(),
+        })
+    }
+
+    /// Append an item to the sequence, returning the amount of bytes written for it.
+    ///
+    /// If serialization fails, the partial item is only discarded if the writer is a
+    /// [`SeekableWriter`](crate::ser::SeekableWriter), such as a
+    /// [`SliceWriter`](crate::ser::SliceWriter) or a `heapless::Vec`.  Other writers keep the
+    /// bytes written before the error, so the sequence ends with an incomplete item that is not
+    /// included in [`written`](Self::written).
+    pub fn push<T: ?Sized + Serialize>(&mut self, item: &T) -> Result<usize> {
+        let written = crate::cbor_serialize_to(item, &mut self.writer)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    /// The total amount of bytes written.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Unwrap the `Writer` from the `SequenceWriter`.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sequence, SequenceWriter, MAGIC};
+    use crate::ser::SliceWriter;
+    use crate::Error;
+
+    #[test]
+    fn sequence() {
+        let mut buf = [0u8; 64];
+        let mut writer = SequenceWriter::with_magic(SliceWriter::new(&mut buf)).unwrap();
+        for item in ["a", "bc", ""] {
+            writer.push(item).unwrap();
+        }
+        let written = writer.written();
+        assert_eq!(written, MAGIC.len() + 2 + 3 + 1);
+
+        let mut seq = Sequence::<&str>::with_magic(&buf[..written]).unwrap();
+        assert_eq!(seq.next(), Some(Ok("a")));
+        assert_eq!(seq.remaining(), &[0x62, b'b', b'c', 0x60]);
+        assert_eq!(seq.next(), Some(Ok("bc")));
+        assert_eq!(seq.next(), Some(Ok("")));
+        assert_eq!(seq.next(), None);
+
+        assert!(Sequence::<u8>::new(&[]).next().is_none());
+        assert_eq!(
+            Sequence::<u8>::with_magic(&buf[..3]).err(),
+            Some(Error::DeserializeIncompleteItem)
+        );
+        assert_eq!(
+            Sequence::<u8>::with_magic(&[0x01]).err(),
+            Some(Error::DeserializeBadMagic)
+        );
+    }
+
+    #[test]
+    fn sequence_partial() {
+        // 1, 1000, then a truncated 100000
+        let input = [0x01, 0x19, 0x03, 0xe8, 0x1a, 0x00, 0x01];
+        let mut seq = Sequence::<u32>::new(&input);
+        assert_eq!(seq.next(), Some(Ok(1)));
+        assert_eq!(seq.next(), Some(Ok(1000)));
+        assert_eq!(seq.next(), Some(Err(Error::DeserializeIncompleteItem)));
+        assert_eq!(seq.next(), None);
+
+        let mut seq = Sequence::<u8>::new(&[0x01, 0x61, b'a', 0x02]);
+        assert_eq!(seq.next(), Some(Ok(1)));
+        assert_eq!(seq.next(), Some(Err(Error::DeserializeBadMajor)));
+        assert_eq!(seq.next(), None);
+    }
+}
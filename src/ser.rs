@@ -2,9 +2,11 @@ use super::error::{Error, Result};
 use serde::ser;
 use serde::Serialize;
 
-use core::mem;
+use core::fmt::{self, Write as _};
 
 use crate::consts::*;
+use crate::raw::RAW_CBOR_NAME;
+use crate::simple::{SIMPLE_NAME, UNDEFINED_NAME};
 
 pub trait Writer {
     /// The type of error returned when a write operation fails.
@@ -12,21 +14,174 @@ pub trait Writer {
 
     /// Attempts to write an entire buffer into this write.
     fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Access this writer as a [`SeekableWriter`][], if it is one.
+    ///
+    /// The serializer uses this to write definite lengths for arrays and maps of unknown
+    /// length.  Implementations of `SeekableWriter` should return `Some(self)`.
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Self::Error>> {
+        None
+    }
+}
+
+/// A `Writer` that can rewrite the data it has written.
+///
+/// When serde does not know the number of items of a sequence or map up front, for example for
+/// iterators or structs with flattened fields, the serializer writes a placeholder header to a
+/// `SeekableWriter`, counts the items and replaces the header with the definite length at the
+/// end.  Other writers get an indefinite-length array or map.
+pub trait SeekableWriter: Writer {
+    /// The number of bytes written so far.
+    fn position(&self) -> usize;
+
+    /// Replace the `len` bytes written at `position` with `data`, moving the bytes after them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position + len` is past the current position.
+    fn splice(&mut self, position: usize, len: usize, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Discard the bytes written after `position`.
+    ///
+    /// This has no effect if `position` is not before the current position.
+    fn rewind(&mut self, position: usize);
+
+    /// Remember the current position, to discard the data written after it with
+    /// [`rollback`](Self::rollback).
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position(),
+        }
+    }
+
+    /// Discard the data written after `checkpoint`, for example after a failed write.
+    fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.rewind(checkpoint.position);
+    }
+}
+
+/// A position of a [`SeekableWriter`][] to roll back to.
+///
+/// ```
+/// use cbor_smol::ser::{SeekableWriter, SliceWriter};
+///
+/// let mut buf = [0u8; 8];
+/// let mut writer = SliceWriter::new(&mut buf);
+/// cbor_smol::cbor_serialize_to(&1u8, &mut writer).unwrap();
+/// let checkpoint = writer.checkpoint();
+/// cbor_smol::cbor_serialize_to(&"two", &mut writer).unwrap();
+/// writer.rollback(checkpoint);
+/// assert_eq!(writer.written(), [0x01]);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    position: usize,
+}
+
+/// Replace `buf[position..position + len]` with `data`, where `buf` holds `written` bytes and
+/// has room for the bytes moved to the end.
+fn splice_bytes(buf: &mut [u8], written: usize, position: usize, len: usize, data: &[u8]) {
+    assert!(position + len <= written, "splice range out of bounds");
+    buf.copy_within(position + len..written, position + data.len());
+    buf[position..position + data.len()].copy_from_slice(data);
+}
+
+/// A `Writer` into a byte slice that can rewrite the data it has written.
+///
+/// This keeps the written part of the slice, so it implements [`SeekableWriter`][].
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Write into `buf` from the start.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        SliceWriter { buf, position: 0 }
+    }
+
+    /// The bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.position]
+    }
+
+    /// Unwrap the bytes written so far.
+    pub fn into_written(self) -> &'a mut [u8] {
+        &mut self.buf[..self.position]
+    }
+
+    /// Discard the bytes written after the first `len` bytes.
+    ///
+    /// This has no effect if `len` is not less than the number of bytes written.
+    pub fn truncate(&mut self, len: usize) {
+        self.position = self.position.min(len);
+    }
 }
 
-impl<'a> Writer for &'a mut [u8] {
+impl Writer for SliceWriter<'_> {
     type Error = Error;
+
     fn write_all(&mut self, buf: &[u8]) -> Result<()> {
-        let l = buf.len();
-        if self.len() < l {
-            // This buffer will not fit in our slice
+        let end = self.position + buf.len();
+        if end > self.buf.len() {
             return Err(Error::SerializeBufferFull);
         }
-        let (current, rem) = mem::take(self).split_at_mut(l);
-        current.copy_from_slice(buf);
-        *self = rem;
+        self.buf[self.position..end].copy_from_slice(buf);
+        self.position = end;
         Ok(())
     }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
+}
+
+impl SeekableWriter for SliceWriter<'_> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn splice(&mut self, position: usize, len: usize, data: &[u8]) -> Result<()> {
+        let new_position = (self.position - len)
+            .checked_add(data.len())
+            .filter(|new_position| *new_position <= self.buf.len())
+            .ok_or(Error::SerializeBufferFull)?;
+        splice_bytes(self.buf, self.position, position, len, data);
+        self.position = new_position;
+        Ok(())
+    }
+
+    fn rewind(&mut self, position: usize) {
+        self.truncate(position);
+    }
+}
+
+/// Implements `SeekableWriter` for a vector of bytes with `len`, `resize` and `truncate`.
+macro_rules! impl_seekable_vec {
+    ($(#[$attr:meta])* $ty:ty) => {
+        $(#[$attr])*
+        impl<const N: usize> SeekableWriter for $ty {
+            fn position(&self) -> usize {
+                self.len()
+            }
+
+            fn splice(&mut self, position: usize, len: usize, data: &[u8]) -> Result<()> {
+                let written = self.len();
+                let new_len = written - len + data.len();
+                if new_len > written {
+                    self.resize(new_len, 0)
+                        .or(Err(Error::SerializeBufferFull))?;
+                }
+                splice_bytes(self, written, position, len, data);
+                self.truncate(new_len);
+                Ok(())
+            }
+
+            fn rewind(&mut self, position: usize) {
+                self.truncate(position);
+            }
+        }
+    };
 }
 
 #[cfg(feature = "heapless-bytes-v0-3")]
@@ -36,8 +191,14 @@ impl<const N: usize> Writer for heapless_bytes_v0_3::Bytes<N> {
         self.extend_from_slice(buf)
             .or(Err(Error::SerializeBufferFull))
     }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
 }
 
+impl_seekable_vec!(#[cfg(feature = "heapless-bytes-v0-3")] heapless_bytes_v0_3::Bytes<N>);
+
 #[cfg(feature = "heapless-bytes-v0-4")]
 impl<const N: usize> Writer for heapless_bytes_v0_4::Bytes<N> {
     type Error = Error;
@@ -45,8 +206,14 @@ impl<const N: usize> Writer for heapless_bytes_v0_4::Bytes<N> {
         self.extend_from_slice(buf)
             .or(Err(Error::SerializeBufferFull))
     }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
 }
 
+impl_seekable_vec!(#[cfg(feature = "heapless-bytes-v0-4")] heapless_bytes_v0_4::Bytes<N>);
+
 #[cfg(feature = "heapless-v0-7")]
 impl<const N: usize> Writer for heapless_v0_7::Vec<u8, N> {
     type Error = Error;
@@ -54,8 +221,14 @@ impl<const N: usize> Writer for heapless_v0_7::Vec<u8, N> {
         self.extend_from_slice(buf)
             .or(Err(Error::SerializeBufferFull))
     }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
 }
 
+impl_seekable_vec!(#[cfg(feature = "heapless-v0-7")] heapless_v0_7::Vec<u8, N>);
+
 #[cfg(feature = "heapless-v0-8")]
 impl<const N: usize> Writer for heapless_v0_8::Vec<u8, N> {
     type Error = Error;
@@ -63,18 +236,91 @@ impl<const N: usize> Writer for heapless_v0_8::Vec<u8, N> {
         self.extend_from_slice(buf)
             .or(Err(Error::SerializeBufferFull))
     }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
+}
+
+impl_seekable_vec!(#[cfg(feature = "heapless-v0-8")] heapless_v0_8::Vec<u8, N>);
+
+#[cfg(feature = "std")]
+impl Writer for std::vec::Vec<u8> {
+    type Error = Error;
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl SeekableWriter for std::vec::Vec<u8> {
+    fn position(&self) -> usize {
+        self.len()
+    }
+
+    fn splice(&mut self, position: usize, len: usize, data: &[u8]) -> Result<()> {
+        self.splice(position..position + len, data.iter().copied());
+        Ok(())
+    }
+
+    fn rewind(&mut self, position: usize) {
+        self.truncate(position);
+    }
 }
 
-impl<'a, T: Writer> Writer for &'a mut T {
+impl<T: Writer> Writer for &mut T {
     type Error = T::Error;
     fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
         (**self).write_all(buf)
     }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Self::Error>> {
+        (**self).seekable()
+    }
 }
 
-struct WrittenWriter<W> {
-    writer: W,
-    written: usize,
+/// A `Writer` that discards all data, used to compute the size of serialized data.
+#[derive(Default)]
+pub(crate) struct NullWriter {
+    position: usize,
+}
+
+impl Writer for NullWriter {
+    type Error = Error;
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.position += buf.len();
+        Ok(())
+    }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        Some(self)
+    }
+}
+
+impl SeekableWriter for NullWriter {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn splice(&mut self, _position: usize, len: usize, data: &[u8]) -> Result<()> {
+        self.position = self.position - len + data.len();
+        Ok(())
+    }
+
+    fn rewind(&mut self, position: usize) {
+        self.position = self.position.min(position);
+    }
+}
+
+/// A `Writer` that counts the bytes written to the inner `Writer`.
+pub(crate) struct WrittenWriter<W> {
+    pub(crate) writer: W,
+    pub(crate) written: usize,
 }
 
 impl<W: Writer> Writer for WrittenWriter<W> {
@@ -84,39 +330,327 @@ impl<W: Writer> Writer for WrittenWriter<W> {
         self.written += buf.len();
         self.writer.write_all(buf)
     }
+
+    // `splice` bypasses the count, the serializer adjusts it
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Self::Error>> {
+        self.writer.seekable()
+    }
 }
 
-pub struct Serializer<W> {
-    inner: WrittenWriter<W>,
+/// A `Writer` that keeps the first error of the inner `Writer`, to return it unchanged.
+pub(crate) struct ErrorWriter<W: Writer> {
+    writer: W,
+    error: Option<W::Error>,
+    // position of the inner writer when this writer was created, if it is seekable
+    start: usize,
+    // bytes written through this writer, its position as a `SeekableWriter`
+    position: usize,
 }
 
-impl<W: Writer> Serializer<W> {
+impl<W: Writer> ErrorWriter<W> {
+    pub(crate) fn new(mut writer: W) -> Self {
+        // the inner writer may have held data before
+        let start = writer.seekable().map_or(0, |writer| writer.position());
+        ErrorWriter {
+            writer,
+            error: None,
+            start,
+            position: 0,
+        }
+    }
+
+    /// The first error of the inner `Writer`, if any.
+    pub(crate) fn into_error(self) -> Option<W::Error> {
+        self.error
+    }
+
+    fn stash(&mut self, err: W::Error) -> Error {
+        self.error = Some(err);
+        // only stops the serializer, the caller returns the original error
+        Error::SerdeSerCustom
+    }
+}
+
+impl<W: Writer> Writer for ErrorWriter<W> {
+    type Error = Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        match self.writer.write_all(buf) {
+            Ok(()) => {
+                self.position += buf.len();
+                Ok(())
+            }
+            Err(err) => Err(self.stash(err)),
+        }
+    }
+
+    fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+        match self.writer.seekable() {
+            Some(_) => Some(self),
+            None => None,
+        }
+    }
+}
+
+impl<W: Writer> SeekableWriter for ErrorWriter<W> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn splice(&mut self, position: usize, len: usize, data: &[u8]) -> Result<()> {
+        let written = self.position;
+        // `seekable` only returns this writer if the inner writer is seekable
+        let writer = self.writer.seekable().ok_or(Error::SerdeSerCustom)?;
+        match writer.splice(self.start + position, len, data) {
+            Ok(()) => {
+                self.position = written - len + data.len();
+                Ok(())
+            }
+            Err(err) => Err(self.stash(err)),
+        }
+    }
+
+    fn rewind(&mut self, position: usize) {
+        if let Some(writer) = self.writer.seekable() {
+            writer.rewind(self.start + position);
+        }
+        self.position = self.position.min(position);
+    }
+}
+
+/// Counts the bytes of formatted text, to write the header of `collect_str` up front.
+struct TextLen(usize);
+
+impl fmt::Write for TextLen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Writes formatted text to an `Encoder`, at most `remaining` bytes.
+struct TextWriter<'a, W> {
+    encoder: &'a mut Encoder<W>,
+    remaining: usize,
+    error: Option<Error>,
+}
+
+impl<W: Writer> fmt::Write for TextWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let result = match self.remaining.checked_sub(s.len()) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                self.encoder.raw(s.as_bytes())
+            }
+            // the second pass produced more text than the first
+            None => Err(Error::SerdeSerCustom),
+        };
+        result.map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+/// A low-level CBOR encoder that writes individual data items to a `Writer`.
+///
+/// This is useful to assemble CBOR structures by hand without declaring a serde type for
+/// them.  Integers, lengths and tags are always encoded in their shortest form.  The caller is
+/// responsible for writing the correct number of items after an array or map header.
+pub struct Encoder<W> {
+    writer: W,
+}
+
+impl<W: Writer> Encoder<W> {
     #[inline]
     pub fn new(writer: W) -> Self {
-        Serializer {
-            inner: WrittenWriter { writer, written: 0 },
-        }
+        Encoder { writer }
     }
 
-    pub fn written(&self) -> usize {
-        self.inner.written
+    /// Get a reference to the underlying `Writer`.
+    #[inline]
+    pub fn writer(&self) -> &W {
+        &self.writer
     }
 
-    /// Unwrap the `Writer` from the `Serializer`.
+    /// Unwrap the `Writer` from the `Encoder`.
     #[inline]
     pub fn into_inner(self) -> W {
-        self.inner.writer
+        self.writer
+    }
+
+    /// Write an unsigned integer (major type 0).
+    #[inline]
+    pub fn uint(&mut self, value: u64) -> Result<()> {
+        self.write_u64(MAJOR_POSINT, value)
+    }
+
+    /// Write the negative integer `-1 - value` (major type 1).
+    #[inline]
+    pub fn negint(&mut self, value: u64) -> Result<()> {
+        self.write_u64(MAJOR_NEGINT, value)
+    }
+
+    /// Write a signed integer, picking major type 0 or 1 as appropriate.
+    #[inline]
+    pub fn int(&mut self, value: i64) -> Result<()> {
+        let sign = (value >> 63) as u64;
+        let major_type = (sign & 0x1) as u8;
+        let bits = sign ^ (value as u64);
+        self.write_u64(major_type, bits)
+    }
+
+    /// Write a definite-length byte string (major type 2).
+    #[inline]
+    pub fn bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.write_u64(MAJOR_BYTES, value.len() as u64)?;
+        self.raw(value)
+    }
+
+    /// Write a bignum (tag 2 or 3) with the minimal big-endian encoding of `magnitude`.
+    fn bignum(&mut self, tag: u64, magnitude: u128) -> Result<()> {
+        let bytes = magnitude.to_be_bytes();
+        let leading = (magnitude.leading_zeros() / 8) as usize;
+        self.tag(tag)?;
+        self.bytes(&bytes[leading..])
+    }
+
+    /// Write a definite-length text string (major type 3).
+    #[inline]
+    pub fn text(&mut self, value: &str) -> Result<()> {
+        self.write_u64(MAJOR_STR, value.len() as u64)?;
+        self.raw(value.as_bytes())
+    }
+
+    /// Write the header of a byte string with `len` bytes, to be followed by the content
+    /// written with [`raw`](Self::raw).
+    #[inline]
+    pub fn bytes_header(&mut self, len: usize) -> Result<()> {
+        self.write_u64(MAJOR_BYTES, len as u64)
+    }
+
+    /// Write the header of a text string with `len` bytes, to be followed by the UTF-8 content
+    /// written with [`raw`](Self::raw).
+    #[inline]
+    pub fn text_header(&mut self, len: usize) -> Result<()> {
+        self.write_u64(MAJOR_STR, len as u64)
+    }
+
+    /// Start an indefinite-length byte string, to be followed by definite-length byte string
+    /// chunks and terminated with [`break_`](Self::break_).
+    #[inline]
+    pub fn indefinite_bytes(&mut self) -> Result<()> {
+        self.raw(&[MAJOR_BYTES << MAJOR_OFFSET | 31])
+    }
+
+    /// Start an indefinite-length text string, to be followed by definite-length text string
+    /// chunks and terminated with [`break_`](Self::break_).
+    #[inline]
+    pub fn indefinite_text(&mut self) -> Result<()> {
+        self.raw(&[MAJOR_STR << MAJOR_OFFSET | 31])
+    }
+
+    /// Write the header of an array with `len` items (major type 4).
+    #[inline]
+    pub fn array_header(&mut self, len: usize) -> Result<()> {
+        self.write_u64(MAJOR_ARRAY, len as u64)
+    }
+
+    /// Write the header of a map with `len` key-value pairs (major type 5).
+    #[inline]
+    pub fn map_header(&mut self, len: usize) -> Result<()> {
+        self.write_u64(MAJOR_MAP, len as u64)
+    }
+
+    /// Start an indefinite-length array, to be terminated with [`break_`](Self::break_).
+    #[inline]
+    pub fn indefinite_array(&mut self) -> Result<()> {
+        self.raw(&[MAJOR_ARRAY << MAJOR_OFFSET | 31])
+    }
+
+    /// Start an indefinite-length map, to be terminated with [`break_`](Self::break_).
+    #[inline]
+    pub fn indefinite_map(&mut self) -> Result<()> {
+        self.raw(&[MAJOR_MAP << MAJOR_OFFSET | 31])
+    }
+
+    /// Write the "break" stop code terminating an indefinite-length item.
+    #[inline]
+    pub fn break_(&mut self) -> Result<()> {
+        self.raw(&[VALUE_BREAK])
+    }
+
+    /// Write a tag (major type 6), to be followed by the tagged item.
+    #[inline]
+    pub fn tag(&mut self, tag: u64) -> Result<()> {
+        self.write_u64(MAJOR_TAG, tag)
+    }
+
+    /// Write a simple value (major type 7).
+    ///
+    /// The values 24 to 31 are reserved by RFC 8949 and rejected.
+    #[inline]
+    pub fn simple(&mut self, value: u8) -> Result<()> {
+        match value {
+            0..=23 => self.raw(&[MAJOR_SIMPLE << MAJOR_OFFSET | value]),
+            24..=31 => Err(Error::SerializeBadSimple),
+            _ => self.raw(&[MAJOR_SIMPLE << MAJOR_OFFSET | 24, value]),
+        }
+    }
+
+    /// Write `true` or `false`.
+    #[inline]
+    pub fn bool(&mut self, value: bool) -> Result<()> {
+        self.raw(&[if value { VALUE_TRUE } else { VALUE_FALSE }])
+    }
+
+    /// Write `null`.
+    #[inline]
+    pub fn null(&mut self) -> Result<()> {
+        self.raw(&[VALUE_NULL])
+    }
+
+    /// Write a floating-point number, using the shortest of half, single or double precision
+    /// that represents the value exactly.
+    pub fn float(&mut self, value: f64) -> Result<()> {
+        if value.is_nan() {
+            // canonical NaN
+            return self.raw(&[MAJOR_FLOAT << MAJOR_OFFSET | 25, 0x7e, 0x00]);
+        }
+        let single = value as f32;
+        if f64::from(single) != value {
+            let mut buf = [MAJOR_FLOAT << MAJOR_OFFSET | 27, 0, 0, 0, 0, 0, 0, 0, 0];
+            buf[1..].copy_from_slice(&value.to_be_bytes());
+            return self.raw(&buf);
+        }
+        match f32_to_f16(single) {
+            Some(half) => {
+                let mut buf = [MAJOR_FLOAT << MAJOR_OFFSET | 25, 0, 0];
+                buf[1..].copy_from_slice(&half.to_be_bytes());
+                self.raw(&buf)
+            }
+            None => {
+                let mut buf = [MAJOR_FLOAT << MAJOR_OFFSET | 26, 0, 0, 0, 0];
+                buf[1..].copy_from_slice(&single.to_be_bytes());
+                self.raw(&buf)
+            }
+        }
+    }
+
+    /// Write pre-encoded CBOR data unchanged.
+    #[inline]
+    pub fn raw(&mut self, data: &[u8]) -> Result<()> {
+        self.writer.write_all(data).map_err(|e| e.into())
     }
 
     #[inline]
     fn write_u8(&mut self, major: u8, value: u8) -> Result<()> {
         if value <= 0x17 {
-            self.inner.write_all(&[major << MAJOR_OFFSET | value])
+            self.raw(&[major << MAJOR_OFFSET | value])
         } else {
             let buf = [major << MAJOR_OFFSET | 24, value];
-            self.inner.write_all(&buf)
+            self.raw(&buf)
         }
-        .map_err(|e| e.into())
     }
 
     #[inline]
@@ -126,7 +660,7 @@ impl<W: Writer> Serializer<W> {
         } else {
             let mut buf = [major << MAJOR_OFFSET | 25, 0, 0];
             buf[1..].copy_from_slice(&value.to_be_bytes());
-            self.inner.write_all(&buf).map_err(|e| e.into())
+            self.raw(&buf)
         }
     }
 
@@ -137,7 +671,7 @@ impl<W: Writer> Serializer<W> {
         } else {
             let mut buf = [major << MAJOR_OFFSET | 26, 0, 0, 0, 0];
             buf[1..].copy_from_slice(&value.to_be_bytes());
-            self.inner.write_all(&buf).map_err(|e| e.into())
+            self.raw(&buf)
         }
     }
 
@@ -148,33 +682,107 @@ impl<W: Writer> Serializer<W> {
         } else {
             let mut buf = [major << MAJOR_OFFSET | 27, 0, 0, 0, 0, 0, 0, 0, 0];
             buf[1..].copy_from_slice(&value.to_be_bytes());
-            self.inner.write_all(&buf).map_err(|e| e.into())
+            self.raw(&buf)
+        }
+    }
+}
+
+/// Convert a single precision float to half precision, if this is possible without loss.
+pub(crate) fn f32_to_f16(value: f32) -> Option<u16> {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    match exponent {
+        // zero (f32 subnormals are too small for f16)
+        0 => (mantissa == 0).then_some(sign),
+        // infinity (NaN is handled by the caller)
+        0xff => Some(sign | 0x7c00),
+        _ => {
+            let exponent = exponent - 127;
+            if (-14..=15).contains(&exponent) {
+                // normal
+                (mantissa & 0x1fff == 0)
+                    .then(|| sign | (((exponent + 15) as u16) << 10) | (mantissa >> 13) as u16)
+            } else if (-24..-14).contains(&exponent) {
+                // subnormal
+                let full = mantissa | 0x80_0000;
+                let shift = (-1 - exponent) as u32;
+                (full & ((1 << shift) - 1) == 0).then(|| sign | (full >> shift) as u16)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+pub struct Serializer<W> {
+    pub(crate) encoder: Encoder<WrittenWriter<W>>,
+    // the next byte string holds pre-encoded data, see `RawCbor`
+    raw: bool,
+    // the next `u8` is a simple value, see `Simple`
+    simple: bool,
+}
+
+impl<W: Writer> Serializer<W> {
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        Serializer {
+            encoder: Encoder::new(WrittenWriter { writer, written: 0 }),
+            raw: false,
+            simple: false,
         }
     }
 
+    pub fn written(&self) -> usize {
+        self.encoder.writer.written
+    }
+
+    /// Unwrap the `Writer` from the `Serializer`.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.encoder.writer.writer
+    }
+
+    /// The `Writer` as a `SeekableWriter`, if it is one.
+    pub(crate) fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = W::Error>> {
+        self.encoder.writer.seekable()
+    }
+
     #[inline]
-    fn serialize_collection(
+    pub(crate) fn serialize_collection(
         &mut self,
         major: u8,
         len: Option<usize>,
     ) -> Result<CollectionSerializer<'_, W>> {
-        let needs_eof = match len {
+        let length = match len {
             Some(len) => {
-                self.write_u64(major, len as u64)?;
-                false
-            }
-            None => {
-                self.inner
-                    .write_all(&[major << MAJOR_OFFSET | 31])
-                    .map_err(|e| e.into())?;
-                true
+                self.encoder.write_u64(major, len as u64)?;
+                CollectionLength::Definite
             }
+            None => match self
+                .encoder
+                .writer
+                .seekable()
+                .map(|writer| writer.position())
+            {
+                Some(position) => {
+                    // a placeholder for the header, patched at the end
+                    self.encoder.raw(&[major << MAJOR_OFFSET])?;
+                    CollectionLength::Patched {
+                        major,
+                        position,
+                        items: 0,
+                    }
+                }
+                None => {
+                    self.encoder.raw(&[major << MAJOR_OFFSET | 31])?;
+                    CollectionLength::Indefinite
+                }
+            },
         };
 
-        Ok(CollectionSerializer {
-            ser: self,
-            needs_eof,
-        })
+        Ok(CollectionSerializer { ser: self, length })
     }
 }
 
@@ -196,8 +804,7 @@ where
 
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<()> {
-        let value = if value { VALUE_TRUE } else { VALUE_FALSE };
-        self.inner.write_all(&[value]).map_err(|e| e.into())
+        self.encoder.bool(value)
     }
 
     #[inline]
@@ -205,7 +812,7 @@ where
         let sign = (value >> 7) as u8;
         let major_type = sign & 0x1;
         let bits = sign ^ (value as u8);
-        self.write_u8(major_type, bits)
+        self.encoder.write_u8(major_type, bits)
     }
 
     #[inline]
@@ -213,7 +820,7 @@ where
         let sign = (value >> 15) as u16;
         let major_type = (sign & 0x1) as u8;
         let bits = sign ^ (value as u16);
-        self.write_u16(major_type, bits)
+        self.encoder.write_u16(major_type, bits)
     }
 
     #[inline]
@@ -221,7 +828,7 @@ where
         let sign = (value >> 31) as u32;
         let major_type = (sign & 0x1) as u8;
         let bits = sign ^ (value as u32);
-        self.write_u32(major_type, bits)
+        self.encoder.write_u32(major_type, bits)
     }
 
     #[inline]
@@ -229,35 +836,62 @@ where
         let sign = (value >> 63) as u64;
         let major_type = (sign & 0x1) as u8;
         let bits = sign ^ (value as u64);
-        self.write_u64(major_type, bits)
+        self.encoder.write_u64(major_type, bits)
+    }
+
+    /// Integers that do not fit into 64 bits are written as bignums (tags 2 and 3).
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        if value >= 0 {
+            return self.serialize_u128(value as u128);
+        }
+        // the magnitude `-1 - value`
+        let magnitude = !value as u128;
+        match u64::try_from(magnitude) {
+            Ok(magnitude) => self.encoder.negint(magnitude),
+            Err(_) => self.encoder.bignum(TAG_NEGATIVE_BIGNUM, magnitude),
+        }
     }
 
     #[inline]
     fn serialize_u8(self, value: u8) -> Result<()> {
-        self.write_u8(MAJOR_POSINT, value)
+        if self.simple {
+            self.simple = false;
+            return self.encoder.simple(value);
+        }
+        self.encoder.write_u8(MAJOR_POSINT, value)
     }
 
     #[inline]
     fn serialize_u16(self, value: u16) -> Result<()> {
-        self.write_u16(MAJOR_POSINT, value)
+        self.encoder.write_u16(MAJOR_POSINT, value)
     }
 
     #[inline]
     fn serialize_u32(self, value: u32) -> Result<()> {
-        self.write_u32(MAJOR_POSINT, value)
+        self.encoder.write_u32(MAJOR_POSINT, value)
     }
 
     #[inline]
     fn serialize_u64(self, value: u64) -> Result<()> {
-        self.write_u64(MAJOR_POSINT, value)
+        self.encoder.write_u64(MAJOR_POSINT, value)
     }
 
-    fn serialize_f32(self, _v: f32) -> Result<()> {
-        todo!("serialize_f32 not implemented");
+    /// Integers that do not fit into 64 bits are written as bignums (tag 2).
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        match u64::try_from(value) {
+            Ok(value) => self.encoder.uint(value),
+            Err(_) => self.encoder.bignum(TAG_POSITIVE_BIGNUM, value),
+        }
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<()> {
-        todo!("serialize_f64 not implemented");
+    #[inline]
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        self.encoder.float(value.into())
+    }
+
+    #[inline]
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        self.encoder.float(value)
     }
 
     #[inline]
@@ -269,19 +903,21 @@ where
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        self.write_u64(MAJOR_STR, value.len() as u64)?;
-        self.inner.write_all(value.as_bytes()).map_err(|e| e.into())
+        self.encoder.text(value)
     }
 
     #[inline]
     fn serialize_bytes(self, value: &[u8]) -> Result<()> {
-        self.write_u64(MAJOR_BYTES, value.len() as u64)?;
-        self.inner.write_all(value).map_err(|e| e.into())
+        if self.raw {
+            self.raw = false;
+            return self.encoder.raw(value);
+        }
+        self.encoder.bytes(value)
     }
 
     #[inline]
     fn serialize_none(self) -> Result<()> {
-        self.inner.write_all(&[VALUE_NULL]).map_err(|e| e.into())
+        self.encoder.null()
     }
 
     #[inline]
@@ -298,7 +934,10 @@ where
     }
 
     #[inline]
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+    fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
+        if name == UNDEFINED_NAME {
+            return self.encoder.simple(SIMPLE_UNDEFINED);
+        }
         self.serialize_unit()
     }
 
@@ -317,10 +956,12 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
+        self.raw = name == RAW_CBOR_NAME;
+        self.simple = name == SIMPLE_NAME;
         // if name == CBOR_NEWTYPE_NAME {
         //     for tag in get_tag().into_iter() {
         //         self.write_u64(6, tag)?;
@@ -344,7 +985,7 @@ where
         //     self.write_u64(5, 1u64)?;
         //     variant.serialize(&mut *self)?;
         // } else {
-        self.write_u64(MAJOR_ARRAY, 2)?;
+        self.encoder.write_u64(MAJOR_ARRAY, 2)?;
         self.serialize_unit_variant(name, variant_index, variant)?;
         // }
         value.serialize(self)
@@ -357,7 +998,7 @@ where
 
     #[inline]
     fn serialize_tuple(self, len: usize) -> Result<&'a mut Serializer<W>> {
-        self.write_u64(MAJOR_ARRAY, len as u64)?;
+        self.encoder.write_u64(MAJOR_ARRAY, len as u64)?;
         Ok(self)
     }
 
@@ -383,7 +1024,7 @@ where
         //     variant.serialize(&mut *self)?;
         //     self.serialize_tuple(len)
         // } else {
-        self.write_u64(MAJOR_ARRAY, (len + 1) as u64)?;
+        self.encoder.write_u64(MAJOR_ARRAY, (len + 1) as u64)?;
         self.serialize_unit_variant(name, variant_index, variant)?;
         Ok(self)
         // }
@@ -396,7 +1037,7 @@ where
 
     #[inline]
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        self.write_u64(MAJOR_MAP, len as u64)?;
+        self.encoder.write_u64(MAJOR_MAP, len as u64)?;
         Ok(self)
     }
 
@@ -411,17 +1052,35 @@ where
         // if self.enum_as_map {
         //     self.write_u64(5, 1u64)?;
         // } else {
-        self.write_u64(MAJOR_ARRAY, 2)?;
+        self.encoder.write_u64(MAJOR_ARRAY, 2)?;
         // }
         self.serialize_unit_variant(name, variant_index, variant)?;
         self.serialize_struct(name, len)
     }
 
-    fn collect_str<T>(self, _value: &T) -> Result<Self::Ok>
+    /// Write the `Display` output as a definite-length text string.
+    ///
+    /// The value is formatted twice: once to measure the length, then to write the text.  Both
+    /// passes must produce the same output.
+    fn collect_str<T>(self, value: &T) -> Result<Self::Ok>
     where
         T: core::fmt::Display + ?Sized,
     {
-        unreachable!()
+        let mut len = TextLen(0);
+        write!(len, "{value}").map_err(|_| Error::SerdeSerCustom)?;
+        self.encoder.text_header(len.0)?;
+        let mut writer = TextWriter {
+            encoder: &mut self.encoder,
+            remaining: len.0,
+            error: None,
+        };
+        if write!(writer, "{value}").is_err() {
+            return Err(writer.error.unwrap_or(Error::SerdeSerCustom));
+        }
+        if writer.remaining != 0 {
+            return Err(Error::SerdeSerCustom);
+        }
+        Ok(())
     }
 
     #[inline]
@@ -430,7 +1089,7 @@ where
     }
 }
 
-impl<'a, W> ser::SerializeTuple for &'a mut Serializer<W>
+impl<W> ser::SerializeTuple for &mut Serializer<W>
 where
     W: Writer,
 {
@@ -451,7 +1110,7 @@ where
     }
 }
 
-impl<'a, W> ser::SerializeTupleStruct for &'a mut Serializer<W>
+impl<W> ser::SerializeTupleStruct for &mut Serializer<W>
 where
     W: Writer,
 {
@@ -472,7 +1131,7 @@ where
     }
 }
 
-impl<'a, W> ser::SerializeTupleVariant for &'a mut Serializer<W>
+impl<W> ser::SerializeTupleVariant for &mut Serializer<W>
 where
     W: Writer,
 {
@@ -493,7 +1152,7 @@ where
     }
 }
 
-impl<'a, W> ser::SerializeStruct for &'a mut Serializer<W>
+impl<W> ser::SerializeStruct for &mut Serializer<W>
 where
     W: Writer,
 {
@@ -516,7 +1175,7 @@ where
     }
 }
 
-impl<'a, W> ser::SerializeStructVariant for &'a mut Serializer<W>
+impl<W> ser::SerializeStructVariant for &mut Serializer<W>
 where
     W: Writer,
 {
@@ -541,20 +1200,61 @@ where
 
 #[doc(hidden)]
 pub struct CollectionSerializer<'a, W> {
-    ser: &'a mut Serializer<W>,
-    needs_eof: bool,
+    pub(crate) ser: &'a mut Serializer<W>,
+    length: CollectionLength,
+}
+
+/// How the length of an array or map is encoded.
+enum CollectionLength {
+    /// The header holds the length.
+    Definite,
+    /// The items are terminated by a break.
+    Indefinite,
+    /// The one-byte header at `position` of a `SeekableWriter` is replaced with the number of
+    /// items at the end.
+    Patched {
+        major: u8,
+        position: usize,
+        items: usize,
+    },
 }
 
 impl<'a, W> CollectionSerializer<'a, W>
 where
     W: Writer,
 {
+    /// Count an array item or map entry for a patched header.
     #[inline]
-    fn end_inner(self) -> Result<()> {
-        if self.needs_eof {
-            self.ser.inner.write_all(&[0xff]).map_err(|e| e.into())
-        } else {
-            Ok(())
+    pub(crate) fn count_item(&mut self) {
+        if let CollectionLength::Patched { items, .. } = &mut self.length {
+            *items += 1;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn end_inner(self) -> Result<()> {
+        match self.length {
+            CollectionLength::Definite => Ok(()),
+            CollectionLength::Indefinite => self.ser.encoder.break_(),
+            CollectionLength::Patched {
+                major,
+                position,
+                items,
+            } => {
+                let mut header = [0; 9];
+                let mut header_writer = SliceWriter::new(&mut header);
+                Encoder::new(&mut header_writer).write_u64(major, items as u64)?;
+                let header = header_writer.written();
+                let writer = &mut self.ser.encoder.writer;
+                writer
+                    .seekable()
+                    // the writer was seekable when the collection started
+                    .ok_or(Error::SerdeSerCustom)?
+                    .splice(position, 1, header)
+                    .map_err(|e| e.into())?;
+                writer.written += header.len() - 1;
+                Ok(())
+            }
         }
     }
 }
@@ -571,6 +1271,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
+        self.count_item();
         value.serialize(&mut *self.ser)
     }
 
@@ -592,6 +1293,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
+        self.count_item();
         key.serialize(&mut *self.ser)
     }
 
@@ -608,3 +1310,419 @@ where
         self.end_inner()
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{Encoder, SliceWriter, Writer};
+    use crate::Error;
+
+    /// A `Writer` that is not seekable, like a stream.
+    pub(crate) struct StreamWriter(pub(crate) Vec<u8>);
+
+    impl Writer for StreamWriter {
+        type Error = Error;
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+            self.0.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    /// A value that fails to serialize.
+    struct Failing;
+
+    impl serde::Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("failing"))
+        }
+    }
+
+    #[test]
+    fn encoder() {
+        let mut buf = [0u8; 64];
+        let mut writer = SliceWriter::new(&mut buf);
+        let mut encoder = Encoder::new(&mut writer);
+        encoder.map_header(2).unwrap();
+        encoder.uint(1).unwrap();
+        encoder.bytes(&[1, 2]).unwrap();
+        encoder.text("rk").unwrap();
+        encoder.bool(true).unwrap();
+        encoder.tag(1).unwrap();
+        encoder.uint(1363896240).unwrap();
+        encoder.indefinite_array().unwrap();
+        encoder.negint(0).unwrap();
+        encoder.int(-500).unwrap();
+        encoder.null().unwrap();
+        encoder.simple(255).unwrap();
+        encoder.break_().unwrap();
+        encoder.raw(&[0xa0]).unwrap();
+        assert_eq!(encoder.simple(24), Err(Error::SerializeBadSimple));
+        assert_eq!(
+            writer.written(),
+            &[
+                0xa2, 0x01, 0x42, 0x01, 0x02, 0x62, b'r', b'k', 0xf5, 0xc1, 0x1a, 0x51, 0x4b, 0x67,
+                0xb0, 0x9f, 0x20, 0x39, 0x01, 0xf3, 0xf6, 0xf8, 0xff, 0xff, 0xa0,
+            ]
+        );
+    }
+
+    #[test]
+    fn encoder_float() {
+        let cases: &[(f64, &[u8])] = &[
+            (0.0, &[0xf9, 0x00, 0x00]),
+            (-0.0, &[0xf9, 0x80, 0x00]),
+            (1.5, &[0xf9, 0x3e, 0x00]),
+            (65504.0, &[0xf9, 0x7b, 0xff]),
+            (5.960464477539063e-8, &[0xf9, 0x00, 0x01]),
+            (0.00006103515625, &[0xf9, 0x04, 0x00]),
+            (100000.0, &[0xfa, 0x47, 0xc3, 0x50, 0x00]),
+            (1.1, &[0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]),
+            (f64::INFINITY, &[0xf9, 0x7c, 0x00]),
+            (f64::NEG_INFINITY, &[0xf9, 0xfc, 0x00]),
+            (f64::NAN, &[0xf9, 0x7e, 0x00]),
+        ];
+        for (value, expected) in cases {
+            let mut buf = [0u8; 9];
+            let mut writer = SliceWriter::new(&mut buf);
+            Encoder::new(&mut writer).float(*value).unwrap();
+            assert_eq!(writer.written(), *expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn collect_str() {
+        use core::cell::Cell;
+        use core::fmt;
+        use serde::{Serialize, Serializer};
+
+        struct Hex(u32);
+
+        impl Serialize for Hex {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(&format_args!("{:x}-{:x}", self.0, self.0 + 1))
+            }
+        }
+
+        let mut buf = [0u8; 32];
+        let ser = crate::cbor_serialize(&Hex(0xabc), &mut buf).unwrap();
+        assert_eq!(ser, b"\x67abc-abd");
+        assert_eq!(crate::cbor_deserialize::<&str>(ser), Ok("abc-abd"));
+        assert_eq!(crate::serialized_size(&Hex(0xabc)), Ok(8));
+        assert_eq!(
+            crate::cbor_serialize(&Hex(0xabc), &mut buf[..4]),
+            Err(Error::SerializeBufferFull)
+        );
+
+        /// Produces longer output each time it is formatted.
+        struct Growing(Cell<usize>);
+
+        impl fmt::Display for Growing {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.set(self.0.get() + 1);
+                (0..self.0.get()).try_for_each(|_| f.write_str("a"))
+            }
+        }
+
+        impl Serialize for Growing {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        assert_eq!(
+            crate::cbor_serialize(&Growing(Cell::new(0)), &mut buf),
+            Err(Error::SerdeSerCustom)
+        );
+    }
+
+    #[test]
+    fn writer_error() {
+        use super::Writer;
+        use crate::{cbor_serialize_to_with_writer_error, SerError};
+
+        #[derive(Debug, PartialEq)]
+        struct FlashError(usize);
+
+        impl From<FlashError> for Error {
+            fn from(_: FlashError) -> Error {
+                Error::SerializeBufferFull
+            }
+        }
+
+        // fails at the write that would exceed its capacity
+        struct Flash {
+            capacity: usize,
+        }
+
+        impl Writer for Flash {
+            type Error = FlashError;
+
+            fn write_all(&mut self, buf: &[u8]) -> Result<(), FlashError> {
+                self.capacity = self
+                    .capacity
+                    .checked_sub(buf.len())
+                    .ok_or(FlashError(self.capacity))?;
+                Ok(())
+            }
+        }
+
+        let value = ("key", [1u32, 70_000]);
+        assert_eq!(
+            cbor_serialize_to_with_writer_error(&value, Flash { capacity: 64 }),
+            Ok(12)
+        );
+        assert_eq!(
+            cbor_serialize_to_with_writer_error(&value, Flash { capacity: 8 }),
+            Err(SerError::Io(FlashError(1)))
+        );
+        assert_eq!(
+            cbor_serialize_to_with_writer_error(
+                &crate::Simple::new(32).unwrap(),
+                Flash { capacity: 8 }
+            ),
+            Ok(2)
+        );
+        // errors of the serialized type are passed through
+        assert_eq!(
+            cbor_serialize_to_with_writer_error(&[Failing], Flash { capacity: 8 }),
+            Err(SerError::Cbor(Error::SerdeSerCustom))
+        );
+    }
+
+    #[test]
+    fn seekable_writer() {
+        use super::{SeekableWriter, SliceWriter, Writer};
+        use crate::{cbor_serialize, cbor_serialize_to, serialized_size};
+        use serde::ser::{SerializeMap, Serializer};
+
+        // a sequence without a length hint
+        struct Seq(u8);
+        impl serde::Serialize for Seq {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq((0..self.0).filter(|_| true))
+            }
+        }
+
+        // a map of sequences without a length
+        struct Map(u8);
+        impl serde::Serialize for Map {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                for i in 0..self.0 {
+                    map.serialize_entry(&i, &Seq(i))?;
+                }
+                map.end()
+            }
+        }
+
+        let mut buf = [0u8; 512];
+        assert_eq!(cbor_serialize(&Seq(3), &mut buf).unwrap(), [0x83, 0, 1, 2]);
+        let ser = cbor_serialize(&Seq(30), &mut buf).unwrap();
+        assert_eq!(ser[..4], [0x98, 30, 0, 1]);
+        assert_eq!(ser.len(), 2 + 24 + 2 * 6);
+        assert_eq!(serialized_size(&Seq(30)), Ok(ser.len()));
+        let len_30 = ser.len();
+
+        let ser = cbor_serialize(&Map(25), &mut buf).unwrap();
+        assert_eq!(ser[..5], [0xb8, 25, 0x00, 0x80, 0x01]);
+        assert_eq!(ser[ser.len() - 28..][..3], [0x18, 24, 0x98]);
+        assert_eq!(serialized_size(&Map(25)), Ok(ser.len()));
+        let len = ser.len();
+        // only the placeholder header fits
+        assert_eq!(
+            cbor_serialize(&Map(25), &mut buf[..len - 1]),
+            Err(Error::SerializeBufferFull)
+        );
+
+        let mut writer = SliceWriter::new(&mut buf);
+        writer.write_all(&[0xff]).unwrap();
+        assert_eq!(
+            crate::cbor_serialize_to_with_writer_error(&Seq(30), &mut writer),
+            Ok(len_30)
+        );
+        assert_eq!(writer.written()[..3], [0xff, 0x98, 30]);
+
+        // other writers get indefinite lengths
+        let mut writer = StreamWriter(Vec::new());
+        assert_eq!(cbor_serialize_to(&Seq(2), &mut writer), Ok(4));
+        assert_eq!(writer.0, [0x9f, 0, 1, 0xff]);
+
+        let mut writer = SliceWriter::new(&mut buf[..6]);
+        writer.write_all(&[1, 2, 3, 4]).unwrap();
+        writer.splice(1, 2, &[5]).unwrap();
+        assert_eq!(writer.written(), [1, 5, 4]);
+        writer.splice(1, 0, &[6, 7, 8]).unwrap();
+        assert_eq!(writer.written(), [1, 6, 7, 8, 5, 4]);
+        assert_eq!(writer.splice(0, 0, &[0]), Err(Error::SerializeBufferFull));
+        assert_eq!(writer.position(), 6);
+
+        #[cfg(feature = "heapless-v0-8")]
+        {
+            let mut vec = heapless_v0_8::Vec::<u8, 64>::new();
+            vec.push(0xff).unwrap();
+            assert_eq!(cbor_serialize_to(&Seq(24), &mut vec), Ok(26));
+            assert_eq!(vec[..4], [0xff, 0x98, 24, 0]);
+        }
+    }
+
+    #[test]
+    fn rollback() {
+        use super::{SeekableWriter, SliceWriter, Writer};
+        use crate::sequence::SequenceWriter;
+        use crate::{cbor_serialize_to, cbor_serialize_to_with_writer_error, SerError};
+
+        let mut buf = [0u8; 16];
+        let mut writer = SliceWriter::new(&mut buf);
+        writer.write_all(&[0xff]).unwrap();
+        assert_eq!(
+            cbor_serialize_to(&("abc", Failing), &mut writer),
+            Err(Error::SerdeSerCustom)
+        );
+        assert_eq!(writer.written(), [0xff]);
+        assert_eq!(
+            cbor_serialize_to(&[0u8; 16], &mut writer),
+            Err(Error::SerializeBufferFull)
+        );
+        assert_eq!(writer.written(), [0xff]);
+        assert_eq!(
+            cbor_serialize_to_with_writer_error(&[0u8; 16], &mut writer),
+            Err(SerError::Io(Error::SerializeBufferFull))
+        );
+        assert_eq!(writer.written(), [0xff]);
+
+        // nested partial writes
+        let checkpoint = writer.checkpoint();
+        writer.write_all(&[1, 2]).unwrap();
+        let inner = writer.checkpoint();
+        writer.write_all(&[3]).unwrap();
+        writer.rollback(inner);
+        assert_eq!(writer.written(), [0xff, 1, 2]);
+        writer.rollback(checkpoint);
+        assert_eq!(writer.written(), [0xff]);
+        writer.rollback(inner);
+        assert_eq!(writer.written(), [0xff]);
+
+        let mut sequence = SequenceWriter::new(&mut writer);
+        assert_eq!(sequence.push(&1u8), Ok(1));
+        assert_eq!(
+            sequence.push(&"too long to fit"),
+            Err(Error::SerializeBufferFull)
+        );
+        assert_eq!(sequence.push(&2u8), Ok(1));
+        assert_eq!(writer.written(), [0xff, 1, 2]);
+        writer.truncate(1);
+        assert_eq!(writer.written(), [0xff]);
+
+        // a writer that keeps what fits of a failed write
+        struct Partial<'a>(SliceWriter<'a>);
+        impl Writer for Partial<'_> {
+            type Error = Error;
+            fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+                for byte in buf {
+                    self.0.write_all(&[*byte])?;
+                }
+                Ok(())
+            }
+            fn seekable(&mut self) -> Option<&mut dyn SeekableWriter<Error = Error>> {
+                Some(self)
+            }
+        }
+        impl SeekableWriter for Partial<'_> {
+            fn position(&self) -> usize {
+                self.0.position()
+            }
+            fn splice(&mut self, position: usize, len: usize, data: &[u8]) -> Result<(), Error> {
+                self.0.splice(position, len, data)
+            }
+            fn rewind(&mut self, position: usize) {
+                self.0.rewind(position)
+            }
+        }
+        let mut partial = Partial(writer);
+        assert_eq!(
+            cbor_serialize_to_with_writer_error(&(1u8, "does not fit at all"), &mut partial),
+            Err(SerError::Io(Error::SerializeBufferFull))
+        );
+        let writer = partial.0;
+        assert_eq!(writer.written(), [0xff]);
+
+        #[cfg(feature = "heapless-v0-8")]
+        {
+            let mut vec = heapless_v0_8::Vec::<u8, 8>::new();
+            vec.extend_from_slice(&[1, 2]).unwrap();
+            assert_eq!(
+                cbor_serialize_to(&"too long", &mut vec),
+                Err(Error::SerializeBufferFull)
+            );
+            assert_eq!(vec, [1, 2]);
+            assert_eq!(
+                cbor_serialize_to(&(1, None::<u8>, Failing), &mut vec),
+                Err(Error::SerdeSerCustom)
+            );
+            assert_eq!(vec, [1, 2]);
+        }
+    }
+
+    #[test]
+    fn seq_fitting() {
+        use crate::{cbor_deserialize, cbor_serialize_seq_fitting};
+
+        let mut buf = [0u8; 16];
+        let items = ["a", "bc", "def", "ghij"];
+        // 1 + 2 + 3 + 4 bytes of items and 5 bytes of strings
+        let (ser, count) = cbor_serialize_seq_fitting(items, &mut buf[..10]).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(
+            ser,
+            [0x83, 0x61, b'a', 0x62, b'b', b'c', 0x63, b'd', b'e', b'f']
+        );
+        assert_eq!(cbor_deserialize::<[&str; 3]>(ser), Ok(["a", "bc", "def"]));
+        let (ser, count) = cbor_serialize_seq_fitting(items, &mut buf).unwrap();
+        assert_eq!((ser.len(), count), (15, 4));
+        // the first item does not fit
+        let (ser, count) = cbor_serialize_seq_fitting(["abcdefgh"], &mut buf[..8]).unwrap();
+        assert_eq!((ser, count), (&[0x80][..], 0));
+        assert_eq!(
+            cbor_serialize_seq_fitting(items, &mut []),
+            Err(Error::SerializeBufferFull)
+        );
+
+        // the header grows to two bytes with the 24th item
+        let mut buf = [0u8; 32];
+        let (ser, count) = cbor_serialize_seq_fitting(0..30, &mut buf[..25]).unwrap();
+        assert_eq!((ser[0], ser.len(), count), (0x97, 24, 23));
+        let (ser, count) = cbor_serialize_seq_fitting(0..30, &mut buf[..26]).unwrap();
+        assert_eq!((&ser[..2], ser.len(), count), (&[0x98, 24][..], 26, 24));
+
+        // other errors are passed through
+        assert_eq!(
+            cbor_serialize_seq_fitting([Failing], &mut buf),
+            Err(Error::SerdeSerCustom)
+        );
+    }
+
+    #[test]
+    fn serialized_size() {
+        use serde::Serialize;
+
+        #[derive(Serialize)]
+        struct Response<'a> {
+            counter: u32,
+            #[serde(with = "serde_bytes")]
+            auth_data: &'a [u8],
+            user: Option<&'a str>,
+            flags: [bool; 3],
+        }
+
+        let response = Response {
+            counter: 70000,
+            auth_data: &[0x42; 300],
+            user: Some("alice"),
+            flags: [true, false, true],
+        };
+        let mut buf = [0u8; 512];
+        let ser = crate::cbor_serialize(&response, &mut buf).unwrap();
+        assert_eq!(crate::serialized_size(&response), Ok(ser.len()));
+        assert_eq!(crate::serialized_size(&0u8), Ok(1));
+        assert_eq!(crate::serialized_size(&u64::MAX), Ok(9));
+    }
+}
@@ -0,0 +1,194 @@
+//! Simple values (major type 7) other than booleans and null.
+
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::error::{Error, Result};
+
+/// The newtype struct name that the cbor-smol serializer and deserializer recognize for
+/// [`Simple`][].
+pub(crate) const SIMPLE_NAME: &str = "\0cbor_smol::Simple";
+
+/// The unit struct name that the cbor-smol serializer and deserializer recognize for
+/// [`Undefined`][].
+pub(crate) const UNDEFINED_NAME: &str = "\0cbor_smol::Undefined";
+
+/// A CBOR simple value.
+///
+/// The values 20 to 23 are false, true, null and undefined, which are represented by `bool`,
+/// `Option` or `()` and [`Undefined`][] instead.  The values 24 to 31 are reserved by RFC 8949.
+/// Neither can be represented by a `Simple`.  Values from 32 on are encoded in two bytes.
+///
+/// Other serializers and deserializers see a newtype struct of a `u8`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Simple(u8);
+
+impl Simple {
+    /// Wrap a simple value, rejecting false, true, null and undefined (20 to 23) and the
+    /// reserved values 24 to 31.
+    pub fn new(value: u8) -> Result<Self> {
+        match value {
+            20..=31 => Err(Error::SerializeBadSimple),
+            value => Ok(Self(value)),
+        }
+    }
+
+    /// The simple value.
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Simple {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<Simple> for u8 {
+    fn from(simple: Simple) -> u8 {
+        simple.0
+    }
+}
+
+impl Serialize for Simple {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(SIMPLE_NAME, &self.0)
+    }
+}
+
+struct SimpleVisitor;
+
+impl<'de> Visitor<'de> for SimpleVisitor {
+    type Value = Simple;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a simple value")
+    }
+
+    fn visit_u8<E: de::Error>(self, v: u8) -> core::result::Result<Simple, E> {
+        Simple::new(v).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v.into()), &self))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> core::result::Result<Simple, D::Error> {
+        let v = u8::deserialize(deserializer)?;
+        self.visit_u8(v)
+    }
+}
+
+impl<'de> Deserialize<'de> for Simple {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(SIMPLE_NAME, SimpleVisitor)
+    }
+}
+
+/// The CBOR value undefined (simple value 23).
+///
+/// Other serializers and deserializers see a unit struct.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Undefined;
+
+impl Serialize for Undefined {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_unit_struct(UNDEFINED_NAME)
+    }
+}
+
+struct UndefinedVisitor;
+
+impl<'de> Visitor<'de> for UndefinedVisitor {
+    type Value = Undefined;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("undefined")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> core::result::Result<Undefined, E> {
+        Ok(Undefined)
+    }
+}
+
+impl<'de> Deserialize<'de> for Undefined {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        deserializer.deserialize_unit_struct(UNDEFINED_NAME, UndefinedVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Simple, Undefined};
+    use crate::{cbor_deserialize, cbor_serialize, Error};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+    struct Values {
+        simple: Option<Simple>,
+        undefined: Undefined,
+    }
+
+    #[test]
+    fn simple() {
+        let mut buf = [0u8; 32];
+        for (value, encoded) in [
+            (0, &[0xe0][..]),
+            (16, &[0xf0]),
+            (19, &[0xf3]),
+            (32, &[0xf8, 0x20]),
+            (255, &[0xf8, 0xff]),
+        ] {
+            let simple = Simple::new(value).unwrap();
+            assert_eq!(cbor_serialize(&simple, &mut buf).unwrap(), encoded);
+            assert_eq!(cbor_deserialize::<Simple>(encoded), Ok(simple));
+        }
+        assert_eq!(Simple::new(22), Err(Error::SerializeBadSimple));
+        assert_eq!(Simple::new(24), Err(Error::SerializeBadSimple));
+        assert_eq!(Simple::try_from(31), Err(Error::SerializeBadSimple));
+
+        // false, true, null, undefined, reserved one-byte values and floats
+        for input in [
+            &[0xf4][..],
+            &[0xf6],
+            &[0xf7],
+            &[0xf8, 0x00],
+            &[0xf8, 0x1f],
+            &[0xf9, 0x00, 0x00],
+        ] {
+            assert_eq!(
+                cbor_deserialize::<Simple>(input),
+                Err(Error::DeserializeBadSimple)
+            );
+        }
+        assert_eq!(
+            cbor_deserialize::<Simple>(&[0x01]),
+            Err(Error::DeserializeBadMajor)
+        );
+
+        let values = Values {
+            simple: Some(Simple::new(99).unwrap()),
+            undefined: Undefined,
+        };
+        let ser = cbor_serialize(&values, &mut buf).unwrap();
+        assert_eq!(&ser[8..10], &[0xf8, 99]);
+        assert_eq!(ser[ser.len() - 1], 0xf7);
+        assert_eq!(cbor_deserialize::<Values>(ser), Ok(values));
+
+        assert_eq!(cbor_serialize(&Undefined, &mut buf).unwrap(), &[0xf7]);
+        assert_eq!(cbor_deserialize::<Undefined>(&[0xf7]), Ok(Undefined));
+        assert_eq!(
+            cbor_deserialize::<Undefined>(&[0xf6]),
+            Err(Error::DeserializeExpectedUndefined)
+        );
+        // null and undefined are distinct
+        assert_eq!(
+            cbor_deserialize::<()>(&[0xf7]),
+            Err(Error::DeserializeExpectedNull)
+        );
+    }
+}
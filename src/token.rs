@@ -0,0 +1,320 @@
+//! A low-level decoder that splits CBOR data into tokens.
+//!
+//! Unlike the serde [`Deserializer`](crate::de::Deserializer), the [`Decoder`][] does not need
+//! to know the structure of the data: it returns the data items and the headers of arrays, maps
+//! and tags one by one.  Nested items are returned after the header of their container, and
+//! indefinite-length items are terminated with [`Token::Break`][].
+
+use crate::consts::*;
+use crate::error::{Error, Result};
+
+/// A single CBOR token.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token<'a> {
+    /// An unsigned integer (major type 0)
+    UInt(u64),
+    /// The negative integer `-1 - n` (major type 1)
+    NegInt(u64),
+    /// A definite-length byte string (major type 2)
+    Bytes(&'a [u8]),
+    /// A definite-length text string (major type 3)
+    Text(&'a str),
+    /// The start of an indefinite-length byte string, followed by byte string chunks
+    BytesIndefinite,
+    /// The start of an indefinite-length text string, followed by text string chunks
+    TextIndefinite,
+    /// The header of an array with the given number of items (major type 4)
+    Array(u64),
+    /// The start of an indefinite-length array
+    ArrayIndefinite,
+    /// The header of a map with the given number of key-value pairs (major type 5)
+    Map(u64),
+    /// The start of an indefinite-length map
+    MapIndefinite,
+    /// A tag (major type 6), followed by the tagged item
+    Tag(u64),
+    Bool(bool),
+    Null,
+    Undefined,
+    /// Any other simple value (major type 7)
+    Simple(u8),
+    /// A half, single or double precision floating-point number (major type 7)
+    Float(f64),
+    /// The "break" stop code terminating an indefinite-length item
+    Break,
+}
+
+/// Splits a byte slice into [`Token`][]s.
+///
+/// The decoder checks that each token is well-formed: reserved additional information values
+/// and reserved simple values are rejected, and text strings must be valid UTF-8.  It does not
+/// check the structure of nested items, see [`validate`](crate::validate) for that.
+pub struct Decoder<'a> {
+    input: &'a [u8],
+    position: usize,
+    minimal: bool,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            position: 0,
+            minimal: false,
+        }
+    }
+
+    /// Only accept integers, lengths, tags and floats in their shortest form, and NaN only as
+    /// `0xf9 0x7e 0x00`.
+    pub fn minimal(mut self, minimal: bool) -> Self {
+        self.minimal = minimal;
+        self
+    }
+
+    /// The amount of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.position
+    }
+
+    /// The complete input of the decoder.
+    pub fn input(&self) -> &'a [u8] {
+        self.input
+    }
+
+    /// The part of the input that has not been decoded yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.input[self.position..]
+    }
+
+    /// Check whether the next token is a break stop code.
+    pub fn at_break(&self) -> bool {
+        self.remaining().first() == Some(&VALUE_BREAK)
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let remaining = self.remaining();
+        if remaining.len() >= count {
+            self.position += count;
+            Ok(&remaining[..count])
+        } else {
+            Err(Error::DeserializeUnexpectedEnd)
+        }
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.take(N)?
+            .try_into()
+            .map_err(|_| Error::InexistentSliceToArrayError)
+    }
+
+    /// Read the argument of a header, `None` for indefinite lengths.
+    fn argument(&mut self, additional: u8) -> Result<Option<u64>> {
+        let (value, minimum) = match additional {
+            0..=23 => return Ok(Some(additional.into())),
+            24 => (self.take_array::<1>()?[0].into(), 24),
+            25 => (u16::from_be_bytes(self.take_array()?).into(), 0x100),
+            26 => (u32::from_be_bytes(self.take_array()?).into(), 0x1_0000),
+            27 => (u64::from_be_bytes(self.take_array()?), 0x1_0000_0000),
+            31 => return Ok(None),
+            _ => return Err(Error::DeserializeBadEncoding),
+        };
+        if self.minimal && value < minimum {
+            return Err(Error::DeserializeNonMinimal);
+        }
+        Ok(Some(value))
+    }
+
+    fn length(&mut self, value: u64) -> Result<&'a [u8]> {
+        let length = usize::try_from(value).map_err(|_| Error::DeserializeUnexpectedEnd)?;
+        self.take(length)
+    }
+
+    /// Decode the next token.
+    pub fn next_token(&mut self) -> Result<Token<'a>> {
+        let initial = self.take_array::<1>()?[0];
+        let major = initial >> MAJOR_OFFSET;
+        let additional = initial & ((1 << MAJOR_OFFSET) - 1);
+
+        if major == MAJOR_SIMPLE {
+            return self.simple(additional);
+        }
+
+        let Some(value) = self.argument(additional)? else {
+            return match major {
+                MAJOR_BYTES => Ok(Token::BytesIndefinite),
+                MAJOR_STR => Ok(Token::TextIndefinite),
+                MAJOR_ARRAY => Ok(Token::ArrayIndefinite),
+                MAJOR_MAP => Ok(Token::MapIndefinite),
+                _ => Err(Error::DeserializeBadEncoding),
+            };
+        };
+        Ok(match major {
+            MAJOR_POSINT => Token::UInt(value),
+            MAJOR_NEGINT => Token::NegInt(value),
+            MAJOR_BYTES => Token::Bytes(self.length(value)?),
+            MAJOR_STR => Token::Text(
+                core::str::from_utf8(self.length(value)?).map_err(|_| Error::DeserializeBadUtf8)?,
+            ),
+            MAJOR_ARRAY => Token::Array(value),
+            MAJOR_MAP => Token::Map(value),
+            _ => Token::Tag(value),
+        })
+    }
+
+    fn simple(&mut self, additional: u8) -> Result<Token<'a>> {
+        Ok(match additional {
+            SIMPLE_FALSE => Token::Bool(false),
+            SIMPLE_TRUE => Token::Bool(true),
+            SIMPLE_NULL => Token::Null,
+            SIMPLE_UNDEFINED => Token::Undefined,
+            0..=19 => Token::Simple(additional),
+            24 => match self.take_array::<1>()?[0] {
+                // reserved, and not well-formed
+                0..=31 => return Err(Error::DeserializeBadEncoding),
+                value => Token::Simple(value),
+            },
+            25 => {
+                let half = u16::from_be_bytes(self.take_array()?);
+                // NaN must be 0xf97e00, without sign or payload
+                let nan = half & 0x7c00 == 0x7c00 && half & 0x03ff != 0;
+                if self.minimal && nan && half != 0x7e00 {
+                    return Err(Error::DeserializeNonMinimal);
+                }
+                Token::Float(f16_to_f64(half))
+            }
+            26 => {
+                let value = f32::from_be_bytes(self.take_array()?);
+                if self.minimal && (value.is_nan() || crate::ser::f32_to_f16(value).is_some()) {
+                    return Err(Error::DeserializeNonMinimal);
+                }
+                Token::Float(value.into())
+            }
+            27 => {
+                let value = f64::from_be_bytes(self.take_array()?);
+                if self.minimal && (value.is_nan() || f64::from(value as f32) == value) {
+                    return Err(Error::DeserializeNonMinimal);
+                }
+                Token::Float(value)
+            }
+            31 => Token::Break,
+            _ => return Err(Error::DeserializeBadEncoding),
+        })
+    }
+}
+
+impl<'a> Iterator for Decoder<'a> {
+    type Item = Result<Token<'a>>;
+
+    /// Decode the next token, or return `None` at the end of the input.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining().is_empty() {
+            None
+        } else {
+            Some(self.next_token())
+        }
+    }
+}
+
+/// Convert a half precision float to double precision.
+pub(crate) fn f16_to_f64(half: u16) -> f64 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * pow2(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1024.0 + mantissa) * pow2(exponent - 25),
+    }
+}
+
+/// `2^exponent` without `f64::powi`, which is not available in `core`.
+fn pow2(exponent: i32) -> f64 {
+    f64::from_bits(((exponent + 1023) as u64) << 52)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, Token};
+    use crate::Error;
+
+    #[test]
+    fn tokens() {
+        let input = [
+            0x84, 0x01, 0x38, 0x63, 0x9f, 0x5f, 0x41, 0x01, 0xff, 0xff, 0xa1, 0x61, b'a', 0xf7,
+            0xc2, 0x40, 0xf8, 0xff, 0xf9, 0x3e, 0x00, 0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99,
+            0x99, 0x9a,
+        ];
+        let tokens: Result<Vec<_>, _> = Decoder::new(&input).collect();
+        assert_eq!(
+            tokens.unwrap(),
+            [
+                Token::Array(4),
+                Token::UInt(1),
+                Token::NegInt(99),
+                Token::ArrayIndefinite,
+                Token::BytesIndefinite,
+                Token::Bytes(&[1]),
+                Token::Break,
+                Token::Break,
+                Token::Map(1),
+                Token::Text("a"),
+                Token::Undefined,
+                Token::Tag(2),
+                Token::Bytes(&[]),
+                Token::Simple(255),
+                Token::Float(1.5),
+                Token::Float(1.1),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_malformed() {
+        for (input, error) in [
+            (&[0x1c][..], Error::DeserializeBadEncoding),
+            (&[0x3f], Error::DeserializeBadEncoding),
+            (&[0xdf], Error::DeserializeBadEncoding),
+            (&[0xfc], Error::DeserializeBadEncoding),
+            (&[0xf8, 0x14], Error::DeserializeBadEncoding),
+            (&[0x62, 0xc3, 0x28], Error::DeserializeBadUtf8),
+            (&[0x19, 0x01], Error::DeserializeUnexpectedEnd),
+            (
+                &[0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                Error::DeserializeUnexpectedEnd,
+            ),
+        ] {
+            assert_eq!(Decoder::new(input).next_token(), Err(error), "{input:02x?}");
+        }
+
+        assert_eq!(Decoder::new(&[0x18, 0x05]).next_token(), Ok(Token::UInt(5)));
+        for input in [
+            &[0x18, 0x05][..],
+            &[0x19, 0x00, 0xff],
+            &[0xfa, 0x3f, 0xc0, 0x00, 0x00],
+            &[0xfb, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0],
+        ] {
+            assert_eq!(
+                Decoder::new(input).minimal(true).next_token(),
+                Err(Error::DeserializeNonMinimal)
+            );
+        }
+    }
+
+    #[test]
+    fn half_floats() {
+        for (half, value) in [
+            (0x0000, 0.0),
+            (0x0001, 5.960464477539063e-8),
+            (0x0400, 0.00006103515625),
+            (0x3c00, 1.0),
+            (0x7bff, 65504.0),
+            (0xc400, -4.0),
+            (0x7c00, f64::INFINITY),
+            (0xfc00, f64::NEG_INFINITY),
+        ] {
+            assert_eq!(super::f16_to_f64(half), value);
+        }
+        assert!(super::f16_to_f64(0x7e00).is_nan());
+    }
+}
@@ -0,0 +1,467 @@
+//! Streaming conversion between CBOR and other self-describing formats such as JSON.
+//!
+//! Items are passed from a `Deserializer` straight to a `Serializer`, without building an
+//! intermediate tree.  The conversion to other formats follows RFC 8949 §6.1:
+//!
+//! - byte strings are written as base64url text without padding,
+//! - tags are dropped and their content is kept,
+//! - null and undefined are written as unit (`null` in JSON),
+//! - negative integers below `i64::MIN` and bignums that fit are written as `i128` or `u128`,
+//!   other bignums as base64url text of their byte string.
+//!
+//! In the other direction, arrays and maps are written with definite length.  As their length is
+//! not known before all items have been converted, the headers are patched in afterwards, so the
+//! output is buffered in memory unless the writer is a [`SeekableWriter`][].  Integers are
+//! written as CBOR integers and all other numbers as the shortest float that represents them
+//! exactly.  Note that `serde_json` parses integers beyond the 64-bit range as floats.
+//!
+//! The JSON functions require the `json` feature.
+//!
+//! [`SeekableWriter`]: crate::ser::SeekableWriter
+
+use core::cell::Cell;
+use core::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::consts::{MAJOR_ARRAY, MAJOR_MAP};
+use crate::error::Error;
+use crate::ser::Writer;
+
+/// Convert a single CBOR item to another format.
+///
+/// The input must hold exactly one item.
+pub fn from_cbor<S: Serializer>(cbor: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut deserializer = crate::de::Deserializer::from_bytes(cbor);
+    let ok = Transcoder::new(&mut deserializer).serialize(serializer)?;
+    if !deserializer.input.is_empty() {
+        return Err(ser::Error::custom(Error::DeserializeTrailingData));
+    }
+    Ok(ok)
+}
+
+/// Convert a single item from another format to CBOR, returning the number of bytes written.
+///
+/// Arrays and maps have definite length.  If `writer` is not a
+/// [`SeekableWriter`](crate::ser::SeekableWriter), the output is buffered and written at once.
+pub fn to_cbor<'de, D: Deserializer<'de>, W: Writer>(
+    deserializer: D,
+    mut writer: W,
+) -> Result<usize, D::Error> {
+    if writer.seekable().is_some() {
+        return write_cbor(deserializer, writer);
+    }
+    let mut buffer = Vec::new();
+    let written = write_cbor(deserializer, &mut buffer)?;
+    writer
+        .write_all(&buffer)
+        .map_err(|err| de::Error::custom(err.into()))?;
+    Ok(written)
+}
+
+fn write_cbor<'de, D: Deserializer<'de>, W: Writer>(
+    deserializer: D,
+    writer: W,
+) -> Result<usize, D::Error> {
+    let mut serializer = crate::ser::Serializer::new(writer);
+    deserializer.deserialize_any(CborVisitor(&mut serializer))?;
+    Ok(serializer.written())
+}
+
+/// Convert a single CBOR item to JSON.
+#[cfg(feature = "json")]
+pub fn cbor_to_json<W: std::io::Write>(cbor: &[u8], writer: W) -> serde_json::Result<()> {
+    from_cbor(cbor, &mut serde_json::Serializer::new(writer))
+}
+
+/// Convert a single CBOR item to indented JSON.
+#[cfg(feature = "json")]
+pub fn cbor_to_json_pretty<W: std::io::Write>(cbor: &[u8], writer: W) -> serde_json::Result<()> {
+    from_cbor(cbor, &mut serde_json::Serializer::pretty(writer))
+}
+
+/// Convert a JSON value to CBOR, returning the number of bytes written.
+#[cfg(feature = "json")]
+pub fn json_to_cbor<W: Writer>(json: &[u8], writer: W) -> serde_json::Result<usize> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let written = to_cbor(&mut deserializer, writer)?;
+    deserializer.end()?;
+    Ok(written)
+}
+
+/// Formats bytes as base64url without padding.
+struct Base64Url<'a>(&'a [u8]);
+
+impl fmt::Display for Base64Url<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        for chunk in self.0.chunks(3) {
+            let mut group = [0; 3];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+            let mut out = [0; 4];
+            for (i, c) in out.iter_mut().enumerate() {
+                *c = ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize];
+            }
+            // n bytes are encoded in n + 1 characters
+            let out = &out[..chunk.len() + 1];
+            f.write_str(core::str::from_utf8(out).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes the next item of a `Deserializer`.
+///
+/// Errors of the serializer are passed through the deserializer, so the visitors store them
+/// and return them in place of the generic error that the deserializer reports.
+struct Transcoder<D>(Cell<Option<D>>);
+
+impl<D> Transcoder<D> {
+    fn new(deserializer: D) -> Self {
+        Transcoder(Cell::new(Some(deserializer)))
+    }
+}
+
+impl<'de, D: Deserializer<'de>> Serialize for Transcoder<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let deserializer = self
+            .0
+            .take()
+            .ok_or_else(|| ser::Error::custom("item already transcoded"))?;
+        let mut error = None;
+        let result = deserializer.deserialize_any(TranscodeVisitor {
+            serializer,
+            error: &mut error,
+        });
+        result.map_err(|err| error.unwrap_or_else(|| ser::Error::custom(err)))
+    }
+}
+
+/// Store a serializer error and return a placeholder for the deserializer.
+fn stash<T, E: de::Error>(error: &mut Option<T>, err: T) -> E {
+    *error = Some(err);
+    E::custom("serializer error")
+}
+
+struct TranscodeVisitor<'a, S: Serializer> {
+    serializer: S,
+    error: &'a mut Option<S::Error>,
+}
+
+impl<S: Serializer> TranscodeVisitor<'_, S> {
+    fn forward<E: de::Error>(
+        self,
+        f: impl FnOnce(S) -> Result<S::Ok, S::Error>,
+    ) -> Result<S::Ok, E> {
+        f(self.serializer).map_err(|err| stash(self.error, err))
+    }
+}
+
+impl<'de, S: Serializer> Visitor<'de> for TranscodeVisitor<'_, S> {
+    type Value = S::Ok;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_i64(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_i128(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_u64(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_u128(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_f64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_str(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<S::Ok, E> {
+        self.forward(|s| s.collect_str(&Base64Url(v)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_unit())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.forward(|s| s.serialize_none())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Ok, D::Error> {
+        self.forward(|s| s.serialize_some(&Transcoder::new(deserializer)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<S::Ok, A::Error> {
+        let mut out = match self.serializer.serialize_seq(seq.size_hint()) {
+            Ok(out) => out,
+            Err(err) => return Err(stash(self.error, err)),
+        };
+        while let Some(()) = seq.next_element_seed(ElementSeed {
+            out: &mut out,
+            error: &mut *self.error,
+        })? {}
+        out.end().map_err(|err| stash(self.error, err))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<S::Ok, A::Error> {
+        let mut out = match self.serializer.serialize_map(map.size_hint()) {
+            Ok(out) => out,
+            Err(err) => return Err(stash(self.error, err)),
+        };
+        while let Some(()) = map.next_key_seed(EntrySeed {
+            out: &mut out,
+            error: &mut *self.error,
+            key: true,
+        })? {
+            map.next_value_seed(EntrySeed {
+                out: &mut out,
+                error: &mut *self.error,
+                key: false,
+            })?;
+        }
+        out.end().map_err(|err| stash(self.error, err))
+    }
+}
+
+struct ElementSeed<'a, T: SerializeSeq> {
+    out: &'a mut T,
+    error: &'a mut Option<T::Error>,
+}
+
+impl<'de, T: SerializeSeq> DeserializeSeed<'de> for ElementSeed<'_, T> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.out
+            .serialize_element(&Transcoder::new(deserializer))
+            .map_err(|err| stash(self.error, err))
+    }
+}
+
+struct EntrySeed<'a, T: SerializeMap> {
+    out: &'a mut T,
+    error: &'a mut Option<T::Error>,
+    key: bool,
+}
+
+impl<'de, T: SerializeMap> DeserializeSeed<'de> for EntrySeed<'_, T> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        let item = Transcoder::new(deserializer);
+        let result = if self.key {
+            self.out.serialize_key(&item)
+        } else {
+            self.out.serialize_value(&item)
+        };
+        result.map_err(|err| stash(self.error, err))
+    }
+}
+
+/// Writes the items visited by another format's `Deserializer` as CBOR.
+struct CborVisitor<'a, W>(&'a mut crate::ser::Serializer<W>);
+
+impl<W: Writer> CborVisitor<'_, W> {
+    fn write<E: de::Error>(
+        self,
+        f: impl FnOnce(&mut crate::ser::Serializer<W>) -> crate::Result<()>,
+    ) -> Result<(), E> {
+        f(self.0).map_err(E::custom)
+    }
+}
+
+impl<'de, W: Writer> Visitor<'de> for CborVisitor<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<(), E> {
+        self.write(|s| s.serialize_bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<(), E> {
+        self.write(|s| s.serialize_i64(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<(), E> {
+        self.write(|s| s.serialize_i128(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<(), E> {
+        self.write(|s| s.serialize_u64(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<(), E> {
+        self.write(|s| s.serialize_u128(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<(), E> {
+        self.write(|s| s.serialize_f64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<(), E> {
+        self.write(|s| s.serialize_str(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<(), E> {
+        self.write(|s| s.serialize_bytes(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.write(|s| s.serialize_unit())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<(), E> {
+        self.write(|s| s.serialize_none())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        // size hints may be wrong, so the header is patched with the actual length
+        let mut out = self
+            .0
+            .serialize_collection(MAJOR_ARRAY, None)
+            .map_err(de::Error::custom)?;
+        while let Some(()) = seq.next_element_seed(CborSeed(&mut *out.ser))? {
+            out.count_item();
+        }
+        out.end_inner().map_err(de::Error::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut out = self
+            .0
+            .serialize_collection(MAJOR_MAP, None)
+            .map_err(de::Error::custom)?;
+        while let Some(()) = map.next_key_seed(CborSeed(&mut *out.ser))? {
+            out.count_item();
+            map.next_value_seed(CborSeed(&mut *out.ser))?;
+        }
+        out.end_inner().map_err(de::Error::custom)
+    }
+}
+
+struct CborSeed<'a, W>(&'a mut crate::ser::Serializer<W>);
+
+impl<'de, W: Writer> DeserializeSeed<'de> for CborSeed<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(CborVisitor(self.0))
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::ser::tests::StreamWriter;
+    use crate::ser::SliceWriter;
+
+    fn json(cbor: &[u8]) -> String {
+        let mut out = Vec::new();
+        cbor_to_json(cbor, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn cbor(json: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        let written = json_to_cbor(json.as_bytes(), &mut out).unwrap();
+        assert_eq!(written, out.len());
+        out
+    }
+
+    #[test]
+    fn to_json() {
+        // {1: h'fbff', "a": [_ -2, 1.5, 1(0), undefined, Infinity]}
+        let input = [
+            0xa2, 0x01, 0x42, 0xfb, 0xff, 0x61, b'a', 0x9f, 0x21, 0xf9, 0x3e, 0x00, 0xc1, 0x00,
+            0xf7, 0xf9, 0x7c, 0x00, 0xff,
+        ];
+        assert_eq!(json(&input), r#"{"1":"-_8","a":[-2,1.5,0,null,null]}"#);
+        // -2^64
+        assert_eq!(
+            json(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            "-18446744073709551616"
+        );
+        assert_eq!(json(&[0x44, 0, 1, 2, 3]), r#""AAECAw""#);
+        // 2^64 as a bignum
+        assert_eq!(
+            json(&[0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]),
+            "18446744073709551616"
+        );
+        // bignums that are not in their shortest form or do not fit
+        assert_eq!(json(&[0xc2, 0x41, 0x01]), "1");
+        assert_eq!(json(&[0xc3, 0x42, 0x00, 0x01]), "-2");
+        let mut input = [0; 19];
+        input[..3].copy_from_slice(&[0xc3, 0x50, 0x80]);
+        assert_eq!(json(&input[..18]), r#""gAAAAAAAAAAAAAAAAAAAAA""#);
+        input[..3].copy_from_slice(&[0xc2, 0x51, 0x01]);
+        assert_eq!(json(&input), r#""AQAAAAAAAAAAAAAAAAAAAAA""#);
+
+        let mut out = Vec::new();
+        for input in [
+            &[0x82, 0x01][..],
+            &[0x01, 0x01],
+            &[0x7f, 0xff],
+            &[0xa1, 0x80, 0x01],
+        ] {
+            assert!(super::cbor_to_json(input, &mut out).is_err());
+        }
+        let err = super::cbor_to_json(&[0x82, 0x01], &mut out).unwrap_err();
+        assert_eq!(err.to_string(), Error::DeserializeUnexpectedEnd.to_string());
+    }
+
+    #[test]
+    fn from_json() {
+        assert_eq!(
+            cbor(r#"{"a": [1, -1, 1.5, "b", null, true], "b": {}}"#),
+            [
+                0xa2, 0x61, b'a', 0x86, 0x01, 0x20, 0xf9, 0x3e, 0x00, 0x61, b'b', 0xf6, 0xf5, 0x61,
+                b'b', 0xa0,
+            ]
+        );
+        let long = cbor(&format!("[{}[]]", "0,".repeat(30)));
+        assert_eq!(long[..2], [0x98, 31]);
+        assert_eq!(long.len(), 2 + 31);
+        assert_eq!(
+            cbor("1e300"),
+            [0xfb, 0x7e, 0x37, 0xe4, 0x3c, 0x88, 0x00, 0x75, 0x9c]
+        );
+
+        // writers that are not seekable
+        let mut writer = StreamWriter(Vec::new());
+        assert_eq!(json_to_cbor(br#"[1, [2]]"#, &mut writer).unwrap(), 4);
+        assert_eq!(writer.0, [0x82, 0x01, 0x81, 0x02]);
+        let mut buf = [0u8; 4];
+        assert!(json_to_cbor(br#"[1, 2, 3, 4]"#, SliceWriter::new(&mut buf)).is_err());
+        assert!(json_to_cbor(b"[1, 2] 3", Vec::new()).is_err());
+
+        let value = r#"{"a":[1,-1,1.5,"b",null,true],"b":{}}"#;
+        assert_eq!(json(&cbor(value)), value);
+    }
+}
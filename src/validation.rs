@@ -0,0 +1,338 @@
+//! Type-agnostic well-formedness checks.
+
+use crate::de::MAX_DEPTH;
+use crate::error::{Error, Result};
+use crate::token::{Decoder, Token};
+
+/// Options for [`validate`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ValidationOptions {
+    /// The maximum nesting depth of arrays, maps and tags, including items embedded in byte
+    /// strings with tag 24.
+    pub max_depth: usize,
+    /// Accept indefinite-length strings, arrays and maps.
+    pub allow_indefinite: bool,
+    /// Check the content of the tags defined in RFC 8949 §3.4, for example that a bignum (tag
+    /// 2 or 3) is a byte string.
+    pub check_tags: bool,
+    /// Require the core deterministic encoding of RFC 8949 §4.2.1: the shortest form for all
+    /// arguments and floats, no indefinite-length items, and strictly ordered map keys.  NaN
+    /// must be encoded as `0xf9 0x7e 0x00` (§4.2.2).
+    pub deterministic: bool,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            allow_indefinite: true,
+            check_tags: true,
+            deterministic: false,
+        }
+    }
+}
+
+/// Check that a byte slice holds exactly one well-formed CBOR item.
+///
+/// This implements the checks of RFC 8949 Appendix C, and in addition checks that text strings
+/// are valid UTF-8 and, depending on the options, the content of tags and the deterministic
+/// encoding.
+pub fn validate(input: &[u8], options: ValidationOptions) -> Result<()> {
+    validate_nested(input, options, options.max_depth)
+}
+
+/// Validate an item with the remaining nesting depth, for items embedded in byte strings.
+fn validate_nested(input: &[u8], options: ValidationOptions, depth: usize) -> Result<()> {
+    let mut validator = Validator {
+        decoder: Decoder::new(input).minimal(options.deterministic),
+        options,
+    };
+    validator.item(depth)?;
+    if !validator.decoder.remaining().is_empty() {
+        return Err(Error::DeserializeTrailingData);
+    }
+    Ok(())
+}
+
+struct Validator<'a> {
+    decoder: Decoder<'a>,
+    options: ValidationOptions,
+}
+
+impl<'a> Validator<'a> {
+    fn nested(depth: usize) -> Result<usize> {
+        depth
+            .checked_sub(1)
+            .ok_or(Error::DeserializeDepthLimitExceeded)
+    }
+
+    fn indefinite(&self) -> Result<()> {
+        if self.options.allow_indefinite && !self.options.deterministic {
+            Ok(())
+        } else {
+            Err(Error::DeserializeIndefiniteLength)
+        }
+    }
+
+    fn consume_break(&mut self) -> Result<bool> {
+        if self.decoder.at_break() {
+            self.decoder.next_token()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn item(&mut self, depth: usize) -> Result<()> {
+        match self.decoder.next_token()? {
+            token @ (Token::BytesIndefinite | Token::TextIndefinite) => {
+                self.indefinite()?;
+                let text = token == Token::TextIndefinite;
+                loop {
+                    match self.decoder.next_token()? {
+                        Token::Break => break,
+                        Token::Bytes(_) if !text => {}
+                        Token::Text(_) if text => {}
+                        _ => return Err(Error::DeserializeBadEncoding),
+                    }
+                }
+            }
+            Token::Array(len) => {
+                let depth = Self::nested(depth)?;
+                for _ in 0..len {
+                    self.item(depth)?;
+                }
+            }
+            Token::ArrayIndefinite => {
+                self.indefinite()?;
+                let depth = Self::nested(depth)?;
+                while !self.consume_break()? {
+                    self.item(depth)?;
+                }
+            }
+            Token::Map(len) => {
+                let depth = Self::nested(depth)?;
+                let mut previous = None;
+                for _ in 0..len {
+                    self.key(depth, &mut previous)?;
+                    self.item(depth)?;
+                }
+            }
+            Token::MapIndefinite => {
+                self.indefinite()?;
+                let depth = Self::nested(depth)?;
+                let mut previous = None;
+                while !self.consume_break()? {
+                    self.key(depth, &mut previous)?;
+                    self.item(depth)?;
+                }
+            }
+            Token::Tag(tag) => {
+                let depth = Self::nested(depth)?;
+                if self.options.check_tags {
+                    self.tag_content(tag, depth)?;
+                }
+                self.item(depth)?;
+            }
+            Token::Break => return Err(Error::DeserializeBadEncoding),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Validate a map key and, for the deterministic encoding, check that it sorts after the
+    /// previous key.
+    fn key(&mut self, depth: usize, previous: &mut Option<&'a [u8]>) -> Result<()> {
+        let start = self.decoder.offset();
+        self.item(depth)?;
+        let key = &self.decoder.input()[start..self.decoder.offset()];
+        if self.options.deterministic {
+            if let Some(previous) = previous {
+                if *previous >= key {
+                    return Err(Error::DeserializeUnsortedKeys);
+                }
+            }
+        }
+        *previous = Some(key);
+        Ok(())
+    }
+
+    /// Check the type of the item following a tag, without consuming it.
+    ///
+    /// `depth` is the remaining nesting depth for the tagged item.
+    fn tag_content(&self, tag: u64, depth: usize) -> Result<()> {
+        let mut decoder = Decoder::new(self.decoder.remaining());
+        let token = decoder.next_token()?;
+        let valid = match tag {
+            // date/time string, URI, base64url, base64, MIME message
+            0 | 32 | 33 | 34 | 36 => matches!(token, Token::Text(_) | Token::TextIndefinite),
+            // epoch-based date/time
+            1 => matches!(token, Token::UInt(_) | Token::NegInt(_) | Token::Float(_)),
+            // bignums
+            2 | 3 => matches!(token, Token::Bytes(_) | Token::BytesIndefinite),
+            // decimal fraction and bigfloat: [exponent, mantissa]
+            4 | 5 => {
+                token == Token::Array(2)
+                    && matches!(decoder.next_token()?, Token::UInt(_) | Token::NegInt(_))
+                    && matches!(
+                        decoder.next_token()?,
+                        Token::UInt(_) | Token::NegInt(_) | Token::Tag(2) | Token::Tag(3)
+                    )
+            }
+            // encoded CBOR data item
+            24 => match token {
+                Token::Bytes(bytes) => match validate_nested(bytes, self.options, depth) {
+                    Err(Error::DeserializeDepthLimitExceeded) => {
+                        return Err(Error::DeserializeDepthLimitExceeded)
+                    }
+                    result => result.is_ok(),
+                },
+                _ => false,
+            },
+            _ => true,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::DeserializeBadTag)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, ValidationOptions};
+    use crate::Error;
+
+    #[test]
+    fn validate_well_formed() {
+        let options = ValidationOptions::default();
+        for input in [
+            &[0x00][..],
+            // [_ h'01', (_ "a" "b"), {_ 1: 2}]
+            &[
+                0x9f, 0x41, 0x01, 0x7f, 0x61, b'a', 0x61, b'b', 0xff, 0xbf, 0x01, 0x02, 0xff, 0xff,
+            ],
+            // {2: 1, 1: 2}
+            &[0xa2, 0x02, 0x01, 0x01, 0x02],
+            // 1(1.5), 2(h'0100'), 4([-2, 27315]), 24(h'01')
+            &[0xc1, 0xf9, 0x3e, 0x00],
+            &[0xc2, 0x42, 0x01, 0x00],
+            &[0xc4, 0x82, 0x21, 0x19, 0x6a, 0xb3],
+            &[0xd8, 0x18, 0x41, 0x01],
+            // non-minimal, but well-formed
+            &[0x19, 0x00, 0x01],
+            &[0xf8, 0xff],
+        ] {
+            assert_eq!(validate(input, options), Ok(()), "{input:02x?}");
+        }
+    }
+
+    #[test]
+    fn validate_malformed() {
+        let options = ValidationOptions::default();
+        for (input, error) in [
+            (&[0x1d][..], Error::DeserializeBadEncoding),
+            (&[0xff], Error::DeserializeBadEncoding),
+            (&[0x82, 0x01, 0xff], Error::DeserializeBadEncoding),
+            (&[0xbf, 0x01, 0xff], Error::DeserializeBadEncoding),
+            (&[0x5f, 0x61, b'a', 0xff], Error::DeserializeBadEncoding),
+            (&[0x7f, 0x7f, 0xff, 0xff], Error::DeserializeBadEncoding),
+            (&[0xf8, 0x18], Error::DeserializeBadEncoding),
+            (&[0x61, 0xff], Error::DeserializeBadUtf8),
+            (&[0x82, 0x01], Error::DeserializeUnexpectedEnd),
+            (&[0x01, 0x01], Error::DeserializeTrailingData),
+            (&[0xc2, 0x01], Error::DeserializeBadTag),
+            (&[0xc0, 0x01], Error::DeserializeBadTag),
+            (&[0xd8, 0x18, 0x41, 0xff], Error::DeserializeBadTag),
+        ] {
+            assert_eq!(validate(input, options), Err(error), "{input:02x?}");
+        }
+
+        let options = ValidationOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert_eq!(validate(&[0x81, 0x81, 0x01], options), Ok(()));
+        assert_eq!(
+            validate(&[0x81, 0xc6, 0x81, 0x01], options),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+
+        // items embedded with tag 24 count towards the depth
+        let mut nested = vec![0x01];
+        for level in 1..=2000 {
+            let mut header = vec![0xd8, 0x18];
+            match nested.len() {
+                len @ 0..=23 => header.push(0x40 | len as u8),
+                len @ 24..=255 => header.extend([0x58, len as u8]),
+                len => header.extend([0x59, (len >> 8) as u8, len as u8]),
+            }
+            nested.splice(0..0, header);
+            if level == 2 {
+                assert_eq!(validate(&nested, options), Ok(()));
+            }
+            if level == 3 {
+                assert_eq!(
+                    validate(&nested, options),
+                    Err(Error::DeserializeDepthLimitExceeded)
+                );
+            }
+        }
+        assert_eq!(
+            validate(&nested, Default::default()),
+            Err(Error::DeserializeDepthLimitExceeded)
+        );
+
+        let options = ValidationOptions {
+            allow_indefinite: false,
+            check_tags: false,
+            ..Default::default()
+        };
+        assert_eq!(validate(&[0xc2, 0x01], options), Ok(()));
+        assert_eq!(
+            validate(&[0x9f, 0xff], options),
+            Err(Error::DeserializeIndefiniteLength)
+        );
+    }
+
+    #[test]
+    fn validate_deterministic() {
+        let options = ValidationOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            validate(&[0xa3, 0x01, 0x00, 0x20, 0x00, 0x61, b'a', 0x00], options),
+            Ok(())
+        );
+        assert_eq!(validate(&[0xf9, 0x7e, 0x00], options), Ok(()));
+        assert_eq!(validate(&[0xf9, 0x7c, 0x00], options), Ok(()));
+        for (input, error) in [
+            (
+                &[0xa2, 0x02, 0x01, 0x01, 0x02][..],
+                Error::DeserializeUnsortedKeys,
+            ),
+            (
+                &[0xa2, 0x01, 0x01, 0x01, 0x02],
+                Error::DeserializeUnsortedKeys,
+            ),
+            (&[0x19, 0x00, 0x01], Error::DeserializeNonMinimal),
+            (
+                &[0xfa, 0x3f, 0xc0, 0x00, 0x00],
+                Error::DeserializeNonMinimal,
+            ),
+            (&[0x9f, 0xff], Error::DeserializeIndefiniteLength),
+            // NaN with a payload or sign
+            (&[0xf9, 0x7e, 0x01], Error::DeserializeNonMinimal),
+            (&[0xf9, 0x7c, 0x01], Error::DeserializeNonMinimal),
+            (&[0xf9, 0xfe, 0x00], Error::DeserializeNonMinimal),
+            (
+                &[0xfa, 0x7f, 0xc0, 0x00, 0x01],
+                Error::DeserializeNonMinimal,
+            ),
+        ] {
+            assert_eq!(validate(input, options), Err(error), "{input:02x?}");
+        }
+    }
+}